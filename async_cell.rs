@@ -0,0 +1,453 @@
+use alloc::collections::VecDeque;
+use core::cell::{Cell, RefCell};
+use core::fmt;
+use core::future::Future;
+use core::mem::ManuallyDrop;
+use core::ops;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+/// A single-threaded cell whose value can be awaited when it is moved out.
+///
+/// `take` and `borrow` return futures that complete once the cell holds a value.
+/// Waiting tasks are served in the order they first polled their future,
+/// and are woken when a value is put back with `replace` or by dropping an `AsyncBorrow`.
+///
+/// Only `core::task::Waker` is used, so this works with any executor.
+pub struct AsyncMoveCell<T> {
+    value: Cell<Option<T>>,
+    waiters: RefCell<VecDeque<Waiter>>,
+    next_ticket: Cell<u64>,
+}
+
+struct Waiter {
+    ticket: u64,
+    waker: Waker,
+}
+
+impl<T> AsyncMoveCell<T> {
+    /// Create a new `AsyncMoveCell` containing the given value.
+    pub const fn new(value: T) -> AsyncMoveCell<T> {
+        AsyncMoveCell {
+            value: Cell::new(Some(value)),
+            waiters: RefCell::new(VecDeque::new()),
+            next_ticket: Cell::new(0),
+        }
+    }
+
+    /// Create a new `AsyncMoveCell` without a value.
+    /// Waiters will be suspended until one is put in with `replace`.
+    pub const fn empty() -> AsyncMoveCell<T> {
+        AsyncMoveCell {
+            value: Cell::new(None),
+            waiters: RefCell::new(VecDeque::new()),
+            next_ticket: Cell::new(0),
+        }
+    }
+
+    /// Consume the `AsyncMoveCell` and return the inner value, if any.
+    pub fn into_inner(self) -> Option<T> {
+        self.value.into_inner()
+    }
+
+    /// Put a value in the cell and return the previous one, if any.
+    ///
+    /// If the cell was empty, this wakes the first waiting task.
+    /// While the value is lent out by an `AsyncBorrow`,
+    /// the value written here is dropped when the borrow puts its own back,
+    /// unless a waiting task takes it first.
+    pub fn replace(&self, value: T) -> Option<T> {
+        let previous = self.value.replace(Some(value));
+        if previous.is_none() {
+            self.wake_first();
+        }
+        previous
+    }
+
+    /// Return whether the cell currently holds a value.
+    pub fn is_available(&self) -> bool {
+        let value = self.value.take();
+        let available = value.is_some();
+        self.value.set(value);
+        available
+    }
+
+    /// Move the value out of the cell without waiting.
+    ///
+    /// Return `None` if the cell is empty or if other tasks are already waiting for the value.
+    pub fn try_take(&self) -> Option<T> {
+        if self.waiters.borrow().is_empty() {
+            self.value.take()
+        } else {
+            None
+        }
+    }
+
+    /// Return a future that moves the value out of the cell once it holds one.
+    ///
+    /// The cell stays empty until a value is put back with `replace`.
+    pub fn take(&self) -> Take<'_, T> {
+        Take {
+            acquire: Acquire { cell: self, ticket: None },
+        }
+    }
+
+    /// Return a future that lends the value out of the cell once it holds one.
+    ///
+    /// The value is put back and the next waiting task is woken when the `AsyncBorrow` is dropped.
+    pub fn borrow(&self) -> BorrowFuture<'_, T> {
+        BorrowFuture {
+            acquire: Acquire { cell: self, ticket: None },
+        }
+    }
+
+    fn wake_first(&self) {
+        let waker = self.waiters.borrow().front().map(|waiter| waiter.waker.clone());
+        if let Some(waker) = waker {
+            waker.wake()
+        }
+    }
+}
+
+impl<T> Default for AsyncMoveCell<T> where T: Default {
+    fn default() -> AsyncMoveCell<T> {
+        AsyncMoveCell::new(T::default())
+    }
+}
+
+impl<T> From<T> for AsyncMoveCell<T> {
+    fn from(value: T) -> AsyncMoveCell<T> {
+        AsyncMoveCell::new(value)
+    }
+}
+
+impl<T> fmt::Debug for AsyncMoveCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AsyncMoveCell { .. }")
+    }
+}
+
+/// The state shared by the `Take` and `BorrowFuture` futures.
+struct Acquire<'a, T: 'a> {
+    cell: &'a AsyncMoveCell<T>,
+    /// Our place in the queue, once we had to wait.
+    ticket: Option<u64>,
+}
+
+impl<'a, T> Acquire<'a, T> {
+    fn poll(&mut self, cx: &mut Context) -> Poll<T> {
+        let mut waiters = self.cell.waiters.borrow_mut();
+        let first = match self.ticket {
+            None => waiters.is_empty(),
+            Some(ticket) => waiters.front().map(|waiter| waiter.ticket) == Some(ticket),
+        };
+        if first {
+            if let Some(value) = self.cell.value.take() {
+                if self.ticket.take().is_some() {
+                    waiters.pop_front();
+                }
+                return Poll::Ready(value)
+            }
+        }
+        match self.ticket {
+            Some(ticket) => {
+                let waiter = waiters.iter_mut().find(|waiter| waiter.ticket == ticket).unwrap();
+                if !waiter.waker.will_wake(cx.waker()) {
+                    waiter.waker = cx.waker().clone()
+                }
+            }
+            None => {
+                let ticket = self.cell.next_ticket.get();
+                self.cell.next_ticket.set(ticket + 1);
+                waiters.push_back(Waiter { ticket, waker: cx.waker().clone() });
+                self.ticket = Some(ticket)
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl<'a, T> Drop for Acquire<'a, T> {
+    fn drop(&mut self) {
+        if let Some(ticket) = self.ticket {
+            let was_first = {
+                let mut waiters = self.cell.waiters.borrow_mut();
+                let index = waiters.iter().position(|waiter| waiter.ticket == ticket).unwrap();
+                waiters.remove(index);
+                index == 0
+            };
+            // We may have been woken for a value we will never take: pass it on.
+            if was_first && self.cell.is_available() {
+                self.cell.wake_first()
+            }
+        }
+    }
+}
+
+/// A future returned by `AsyncMoveCell::take`.
+pub struct Take<'a, T: 'a> {
+    acquire: Acquire<'a, T>,
+}
+
+impl<'a, T> Future for Take<'a, T> {
+    type Output = T;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<T> {
+        self.acquire.poll(cx)
+    }
+}
+
+impl<'a, T> fmt::Debug for Take<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("movecell::Take(..)")
+    }
+}
+
+/// A future returned by `AsyncMoveCell::borrow`.
+pub struct BorrowFuture<'a, T: 'a> {
+    acquire: Acquire<'a, T>,
+}
+
+impl<'a, T> Future for BorrowFuture<'a, T> {
+    type Output = AsyncBorrow<'a, T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<AsyncBorrow<'a, T>> {
+        let cell = self.acquire.cell;
+        self.acquire.poll(cx).map(|value| AsyncBorrow {
+            _cell: cell,
+            _value: ManuallyDrop::new(value),
+        })
+    }
+}
+
+impl<'a, T> fmt::Debug for BorrowFuture<'a, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("movecell::BorrowFuture(..)")
+    }
+}
+
+/// A value lent out of an `AsyncMoveCell`.
+/// It is put back, and the next waiting task woken, when this is dropped.
+///
+/// If another value was put in the cell with `replace` in the meantime,
+/// and not taken by a waiting task, the lent value takes its place and that value is dropped.
+pub struct AsyncBorrow<'a, T: 'a> {
+    _cell: &'a AsyncMoveCell<T>,
+    _value: ManuallyDrop<T>,
+}
+
+impl<'a, T> AsyncBorrow<'a, T> {
+    /// Keep the value instead of putting it back, leaving the cell empty.
+    pub fn into_inner(self) -> T {
+        let mut this = ManuallyDrop::new(self);
+        unsafe { ManuallyDrop::take(&mut this._value) }
+    }
+}
+
+impl<'a, T> Drop for AsyncBorrow<'a, T> {
+    fn drop(&mut self) {
+        let value = unsafe { ManuallyDrop::take(&mut self._value) };
+        self._cell.replace(value);
+    }
+}
+
+impl<'a, T> ops::Deref for AsyncBorrow<'a, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self._value
+    }
+}
+
+impl<'a, T> ops::DerefMut for AsyncBorrow<'a, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self._value
+    }
+}
+
+impl<'a, T> fmt::Debug for AsyncBorrow<'a, T> where T: fmt::Debug {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("movecell::AsyncBorrow").field(&*self._value).finish()
+    }
+}
+
+
+/// A waker for tests that counts wake-ups. Futures are polled by hand with `poll_once`.
+#[cfg(test)]
+struct TestWaker(::std::sync::atomic::AtomicUsize);
+
+#[cfg(test)]
+impl ::std::task::Wake for TestWaker {
+    fn wake(self: ::std::sync::Arc<Self>) {
+        self.0.fetch_add(1, ::std::sync::atomic::Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+impl TestWaker {
+    fn new() -> (::std::sync::Arc<TestWaker>, Waker) {
+        let counter = ::std::sync::Arc::new(TestWaker(Default::default()));
+        let waker = Waker::from(counter.clone());
+        (counter, waker)
+    }
+
+    fn wakes(&self) -> usize {
+        self.0.load(::std::sync::atomic::Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+fn poll_once<F: Future + Unpin>(future: &mut F, waker: &Waker) -> Poll<F::Output> {
+    Pin::new(future).poll(&mut Context::from_waker(waker))
+}
+
+/// A minimal single-threaded executor for tests.
+/// Tasks are polled when they are woken, in the order they were woken.
+#[cfg(test)]
+struct TestExecutor<'a> {
+    tasks: Vec<Option<Pin<Box<dyn Future<Output = ()> + 'a>>>>,
+    woken: ::std::sync::Arc<::std::sync::Mutex<VecDeque<usize>>>,
+}
+
+#[cfg(test)]
+struct TaskWaker {
+    task: usize,
+    woken: ::std::sync::Arc<::std::sync::Mutex<VecDeque<usize>>>,
+}
+
+#[cfg(test)]
+impl ::std::task::Wake for TaskWaker {
+    fn wake(self: ::std::sync::Arc<Self>) {
+        self.woken.lock().unwrap().push_back(self.task)
+    }
+}
+
+#[cfg(test)]
+impl<'a> TestExecutor<'a> {
+    fn new() -> TestExecutor<'a> {
+        TestExecutor { tasks: Vec::new(), woken: Default::default() }
+    }
+
+    /// Add a task, to be polled for the first time by the next `run`.
+    fn spawn<F: Future<Output = ()> + 'a>(&mut self, future: F) {
+        self.woken.lock().unwrap().push_back(self.tasks.len());
+        self.tasks.push(Some(Box::pin(future)))
+    }
+
+    /// Poll woken tasks until none is left, and return how many tasks are not finished.
+    fn run(&mut self) -> usize {
+        loop {
+            let task = self.woken.lock().unwrap().pop_front();
+            let task = match task {
+                Some(task) => task,
+                None => return self.tasks.iter().filter(|task| task.is_some()).count(),
+            };
+            let waker = Waker::from(::std::sync::Arc::new(TaskWaker { task, woken: self.woken.clone() }));
+            if let Some(ref mut future) = self.tasks[task] {
+                if future.as_mut().poll(&mut Context::from_waker(&waker)).is_ready() {
+                    self.tasks[task] = None
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn async_move_cell_take() {
+    let cell = AsyncMoveCell::new(1);
+    let (counter, waker) = TestWaker::new();
+    assert_eq!(poll_once(&mut cell.take(), &waker), Poll::Ready(1));
+    assert!(!cell.is_available());
+
+    let mut first = cell.take();
+    let mut second = cell.take();
+    assert_eq!(poll_once(&mut first, &waker), Poll::Pending);
+    assert_eq!(poll_once(&mut second, &waker), Poll::Pending);
+    assert_eq!(cell.try_take(), None);
+
+    assert_eq!(cell.replace(2), None);
+    assert_eq!(counter.wakes(), 1);
+    assert_eq!(poll_once(&mut second, &waker), Poll::Pending);
+    assert_eq!(poll_once(&mut first, &waker), Poll::Ready(2));
+
+    cell.replace(3);
+    assert_eq!(counter.wakes(), 2);
+    assert_eq!(poll_once(&mut second, &waker), Poll::Ready(3));
+    drop((first, second));
+    assert_eq!(cell.into_inner(), None);
+}
+
+#[test]
+fn async_move_cell_borrow() {
+    let cell = AsyncMoveCell::new(vec![1]);
+    let (counter, waker) = TestWaker::new();
+    let mut guard = match poll_once(&mut cell.borrow(), &waker) {
+        Poll::Ready(guard) => guard,
+        Poll::Pending => panic!("cell should be available"),
+    };
+    guard.push(2);
+
+    let mut first = cell.borrow();
+    let mut second = cell.take();
+    assert!(poll_once(&mut first, &waker).is_pending());
+    assert!(poll_once(&mut second, &waker).is_pending());
+    drop(guard);
+    assert_eq!(counter.wakes(), 1);
+
+    // Dropping a woken waiter passes the wake-up on to the next one.
+    drop(first);
+    assert_eq!(counter.wakes(), 2);
+    assert_eq!(poll_once(&mut second, &waker), Poll::Ready(vec![1, 2]));
+
+    cell.replace(vec![3]);
+    let guard = match poll_once(&mut cell.borrow(), &waker) {
+        Poll::Ready(guard) => guard,
+        Poll::Pending => panic!("cell should be available"),
+    };
+    assert_eq!(guard.into_inner(), [3]);
+    assert!(!cell.is_available());
+
+    // A value written while lent is dropped when the borrow puts its own back.
+    cell.replace(vec![4]);
+    let guard = match poll_once(&mut cell.borrow(), &waker) {
+        Poll::Ready(guard) => guard,
+        Poll::Pending => panic!("cell should be available"),
+    };
+    assert_eq!(cell.replace(vec![5]), None);
+    drop(guard);
+    assert_eq!(cell.try_take(), Some(vec![4]));
+}
+
+#[test]
+fn async_move_cell_tasks() {
+    use core::future::poll_fn;
+
+    // The crate uses the 2015 edition, without `async` blocks: tasks are written with `poll_fn`.
+    fn borrow_and_push<'a>(cell: &'a AsyncMoveCell<Vec<u32>>, id: u32) -> impl Future<Output = ()> + 'a {
+        let mut borrow = cell.borrow();
+        poll_fn(move |cx| Pin::new(&mut borrow).poll(cx).map(|mut guard| guard.push(id)))
+    }
+
+    let cell = AsyncMoveCell::new(Vec::new());
+    let taken = Cell::new(None);
+    let value = cell.try_take().unwrap();
+    {
+        let mut executor = TestExecutor::new();
+        executor.spawn(borrow_and_push(&cell, 1));
+        executor.spawn(borrow_and_push(&cell, 2));
+        let mut take = cell.take();
+        let taken = &taken;
+        executor.spawn(poll_fn(move |cx| Pin::new(&mut take).poll(cx).map(|value| taken.set(Some(value)))));
+        executor.spawn(borrow_and_push(&cell, 4));
+        assert_eq!(executor.run(), 4);
+
+        // Each task puts the value back and wakes the next one, in the order they first waited.
+        cell.replace(value);
+        assert_eq!(executor.run(), 1);
+        assert!(!cell.is_available());
+        cell.replace(vec![3]);
+        assert_eq!(executor.run(), 0);
+    }
+    assert_eq!(taken.into_inner(), Some(vec![1, 2]));
+    assert_eq!(cell.into_inner(), Some(vec![3, 4]));
+}
@@ -0,0 +1,245 @@
+use alloc::boxed::Box;
+use alloc::sync::Arc;
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ptr;
+use core::sync::atomic::{AtomicPtr, Ordering};
+use Placeholder;
+
+/// An owning pointer that can be stored in an `AtomicMoveCell`.
+///
+/// # Safety
+///
+/// `from_raw` must turn a pointer returned by `into_raw` back into the original value,
+/// and a pointer returned by `into_raw` must not be shared with any other value
+/// unless the type is reference-counted like `Arc`.
+pub unsafe trait AtomicPointer {
+    /// The type pointed to.
+    type Target;
+
+    /// Convert into a raw pointer that owns the value.
+    fn into_raw(self) -> *mut Self::Target;
+
+    /// Convert back a raw pointer returned by `into_raw`.
+    ///
+    /// # Safety
+    ///
+    /// `ptr` must come from `into_raw` for the same type,
+    /// and this must be called at most once per `into_raw` call.
+    unsafe fn from_raw(ptr: *mut Self::Target) -> Self;
+}
+
+unsafe impl<T> AtomicPointer for Box<T> {
+    type Target = T;
+
+    #[inline]
+    fn into_raw(self) -> *mut T {
+        Box::into_raw(self)
+    }
+
+    #[inline]
+    unsafe fn from_raw(ptr: *mut T) -> Box<T> {
+        Box::from_raw(ptr)
+    }
+}
+
+unsafe impl<T> AtomicPointer for Arc<T> {
+    type Target = T;
+
+    #[inline]
+    fn into_raw(self) -> *mut T {
+        Arc::into_raw(self) as *mut T
+    }
+
+    #[inline]
+    unsafe fn from_raw(ptr: *mut T) -> Arc<T> {
+        Arc::from_raw(ptr)
+    }
+}
+
+macro_rules! option_impl {
+    ($pointer: ident) => {
+        /// `None` is represented as a null pointer.
+        unsafe impl<T> AtomicPointer for Option<$pointer<T>> {
+            type Target = T;
+
+            #[inline]
+            fn into_raw(self) -> *mut T {
+                match self {
+                    Some(pointer) => pointer.into_raw(),
+                    None => ptr::null_mut(),
+                }
+            }
+
+            #[inline]
+            unsafe fn from_raw(ptr: *mut T) -> Option<$pointer<T>> {
+                if ptr.is_null() {
+                    None
+                } else {
+                    Some($pointer::from_raw(ptr))
+                }
+            }
+        }
+    }
+}
+
+option_impl!(Box);
+option_impl!(Arc);
+
+/// A lock-free counterpart to `MoveCell` for owning pointers
+/// such as `Box<T>`, `Arc<T>` or `Option` of either,
+/// where each operation is a single atomic pointer operation.
+///
+/// Like `AtomicPtr`, each method takes the memory orderings to use.
+pub struct AtomicMoveCell<P: AtomicPointer> {
+    ptr: AtomicPtr<P::Target>,
+    _marker: PhantomData<P>,
+}
+
+unsafe impl<P: AtomicPointer + Send> Sync for AtomicMoveCell<P> {}
+
+impl<P: AtomicPointer> AtomicMoveCell<P> {
+    /// Create a new `AtomicMoveCell` containing the given value.
+    #[inline]
+    pub fn new(value: P) -> AtomicMoveCell<P> {
+        AtomicMoveCell {
+            ptr: AtomicPtr::new(value.into_raw()),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Consume the `AtomicMoveCell` and return the inner value.
+    #[inline]
+    pub fn into_inner(self) -> P {
+        let ptr = self.ptr.load(Ordering::Relaxed);
+        mem::forget(self);
+        unsafe { P::from_raw(ptr) }
+    }
+
+    /// Return the inner value after replacing it with the given value.
+    #[inline]
+    pub fn swap(&self, value: P, order: Ordering) -> P {
+        unsafe { P::from_raw(self.ptr.swap(value.into_raw(), order)) }
+    }
+
+    /// Set the inner value, dropping the previous one.
+    #[inline]
+    pub fn store(&self, value: P, order: Ordering) {
+        drop(self.swap(value, order))
+    }
+
+    /// Replace the inner value with `new` if it is currently the pointer `current`,
+    /// and return the previous value.
+    /// Otherwise, return `new` back as an error.
+    ///
+    /// Only the pointer address is compared, see `load_ptr`.
+    #[inline]
+    pub fn compare_and_replace(&self, current: *const P::Target, new: P,
+                               success: Ordering, failure: Ordering) -> Result<P, P> {
+        let new = new.into_raw();
+        match self.ptr.compare_exchange(current as *mut P::Target, new, success, failure) {
+            Ok(previous) => Ok(unsafe { P::from_raw(previous) }),
+            Err(_) => Err(unsafe { P::from_raw(new) }),
+        }
+    }
+
+    /// Return the address of the inner value, for use with `compare_and_replace`.
+    ///
+    /// The value may be replaced and dropped at any time by another thread,
+    /// so the pointer must not be dereferenced.
+    #[inline]
+    pub fn load_ptr(&self, order: Ordering) -> *const P::Target {
+        self.ptr.load(order)
+    }
+}
+
+impl<P: AtomicPointer + Placeholder> AtomicMoveCell<P> {
+    /// Return the inner value after replacing it with a placeholder value,
+    /// such as `None` for `Option<Box<T>>`.
+    #[inline]
+    pub fn take(&self, order: Ordering) -> P {
+        self.swap(P::placeholder(), order)
+    }
+}
+
+impl<P: AtomicPointer> Drop for AtomicMoveCell<P> {
+    fn drop(&mut self) {
+        drop(unsafe { P::from_raw(*self.ptr.get_mut()) })
+    }
+}
+
+impl<P: AtomicPointer + Default> Default for AtomicMoveCell<P> {
+    #[inline]
+    fn default() -> AtomicMoveCell<P> {
+        AtomicMoveCell::new(P::default())
+    }
+}
+
+impl<P: AtomicPointer> fmt::Debug for AtomicMoveCell<P> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "AtomicMoveCell({:p})", self.ptr.load(Ordering::Relaxed))
+    }
+}
+
+
+#[test]
+fn atomic_move_cell() {
+    use core::sync::atomic::Ordering::*;
+
+    let x = AtomicMoveCell::new(Box::new(1));
+    assert_eq!(*x.swap(Box::new(2), AcqRel), 1);
+    assert_eq!(*x.take(AcqRel), 2);
+    assert_eq!(*x.into_inner(), 0);
+
+    let x = AtomicMoveCell::new(Some(Arc::new("first")));
+    let first = x.take(AcqRel).unwrap();
+    assert!(x.take(AcqRel).is_none());
+    assert!(x.load_ptr(Acquire).is_null());
+    x.store(Some(first.clone()), Release);
+    assert_eq!(Arc::strong_count(&first), 2);
+
+    let second = Some(Arc::new("second"));
+    let second = x.compare_and_replace(ptr::null(), second, AcqRel, Acquire).unwrap_err();
+    let previous = x.compare_and_replace(Arc::as_ptr(&first), second, AcqRel, Acquire);
+    assert!(Arc::ptr_eq(&previous.unwrap().unwrap(), &first));
+    assert_eq!(*x.take(AcqRel).unwrap(), "second");
+    x.store(Some(first.clone()), Release);
+    drop(x);
+    assert_eq!(Arc::strong_count(&first), 1);
+}
+
+#[test]
+fn atomic_move_cell_threads() {
+    use core::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    const THREADS: usize = 8;
+    const ITERATIONS: usize = 10_000;
+
+    struct Counted<'a>(&'a AtomicUsize);
+
+    impl<'a> Drop for Counted<'a> {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let dropped = AtomicUsize::new(0);
+    let cell = AtomicMoveCell::new(None);
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for i in 0..ITERATIONS {
+                    if i % 2 == 0 {
+                        cell.store(Some(Box::new(Counted(&dropped))), Ordering::AcqRel);
+                    } else {
+                        drop(cell.take(Ordering::AcqRel));
+                    }
+                }
+            });
+        }
+    });
+    drop(cell);
+    assert_eq!(dropped.into_inner(), THREADS * ITERATIONS / 2);
+}
@@ -0,0 +1,52 @@
+//! Compare `MoveCell::borrow`, which moves the value out and back,
+//! with `MoveCell::with_mut`, which lends it in place.
+//!
+//! Run with `cargo bench`.
+
+extern crate movecell;
+
+use movecell::MoveCell;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 100_000;
+
+/// A 4 KB state block.
+struct State([u64; 512]);
+
+impl Default for State {
+    fn default() -> State {
+        State([0; 512])
+    }
+}
+
+fn bench<F: FnMut()>(name: &str, mut f: F) -> Duration {
+    for _ in 0..ITERATIONS / 10 {
+        f()
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f()
+    }
+    let elapsed = start.elapsed();
+    println!("{:<20} {:>8} ns/iter", name, elapsed.as_nanos() / u128::from(ITERATIONS));
+    elapsed
+}
+
+fn main() {
+    let cell = MoveCell::new(State([1; 512]));
+    let borrow = bench("borrow", || {
+        let mut state = black_box(&cell).borrow();
+        black_box(&mut *state).0[7] += 1;
+    });
+    let with_mut = bench("with_mut", || {
+        black_box(&cell).with_mut(|state| black_box(state).0[7] += 1);
+    });
+    bench("borrow_mut", || {
+        let mut state = black_box(&cell).borrow_mut();
+        black_box(&mut *state).0[7] += 1;
+    });
+    println!("with_mut is {:.1}x faster than borrow",
+             borrow.as_secs_f64() / with_mut.as_secs_f64());
+    assert_eq!(cell.into_inner().0[7], 1 + 3 * u64::from(ITERATIONS + ITERATIONS / 10));
+}
@@ -0,0 +1,51 @@
+use {BorrowError, MoveCell};
+
+impl<F> MoveCell<F> {
+    /// Call the closure in the cell in place with the given argument.
+    /// Use a tuple to pass several arguments.
+    ///
+    /// Return an error if the closure is already borrowed,
+    /// for example when it calls itself through the cell.
+    #[inline]
+    #[track_caller]
+    pub fn call_mut<A, R>(&self, arg: A) -> Result<R, BorrowError> where F: FnMut(A) -> R {
+        self.try_with_mut(|f| f(arg))
+    }
+
+    /// Return an `Fn` closure that calls the `FnMut` closure in the cell with `call_mut`,
+    /// for APIs that only accept `Fn`.
+    #[inline]
+    pub fn as_fn<A, R>(&self) -> impl Fn(A) -> Result<R, BorrowError> + '_ where F: FnMut(A) -> R {
+        move |arg| self.call_mut(arg)
+    }
+}
+
+
+#[test]
+fn call_mut() {
+    let mut events = Vec::new();
+    {
+        let handler = MoveCell::new(|event: u32| {
+            events.push(event);
+            events.len()
+        });
+        assert_eq!(handler.call_mut(1).unwrap(), 1);
+        let as_fn = handler.as_fn();
+        assert_eq!(as_fn(2).unwrap(), 2);
+        fn call_twice<G: Fn(u32) -> R, R>(g: G) -> R {
+            g(3);
+            g(4)
+        }
+        assert_eq!(call_twice(handler.as_fn()).unwrap(), 4);
+    }
+    assert_eq!(events, [1, 2, 3, 4]);
+
+    struct Handler(MoveCell<fn(&Handler) -> bool>);
+
+    fn reenter(handler: &Handler) -> bool {
+        handler.0.call_mut(handler).is_err()
+    }
+
+    let handler = Handler(MoveCell::new(reenter));
+    assert!(handler.0.call_mut(&handler).unwrap());
+}
@@ -0,0 +1,181 @@
+use alloc::boxed::Box;
+use alloc::vec::Vec;
+use core::cell::Cell;
+use core::fmt;
+use MoveCell;
+
+/// A list of callbacks that can be modified while it is being dispatched.
+///
+/// During `dispatch`, callbacks may add or remove callbacks and dispatch again:
+///
+/// * Callbacks added during a dispatch are not called by that dispatch, only by later ones.
+/// * Removals take effect immediately: a removed callback is not called again,
+///   even later in the current dispatch. A callback that removes itself is dropped when it returns.
+/// * A nested dispatch calls every callback except those that are currently running,
+///   which are skipped rather than called reentrantly.
+pub struct CallbackList<A> {
+    /// Sorted by id. The callback is `None` while it is running.
+    entries: MoveCell<Vec<Entry<A>>>,
+    next_id: Cell<u64>,
+}
+
+type Callback<A> = Box<dyn FnMut(&A)>;
+
+struct Entry<A> {
+    id: u64,
+    callback: Option<Callback<A>>,
+}
+
+/// Identifies a callback added to a `CallbackList`, to remove it later.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct CallbackHandle {
+    id: u64,
+}
+
+impl<A> CallbackList<A> {
+    /// Create an empty `CallbackList`.
+    pub const fn new() -> CallbackList<A> {
+        CallbackList {
+            entries: MoveCell::new(Vec::new()),
+            next_id: Cell::new(0),
+        }
+    }
+
+    /// Add a callback to the list. If a dispatch is in progress, it does not call this callback.
+    pub fn add<F>(&self, callback: F) -> CallbackHandle where F: FnMut(&A) + 'static {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        self.entries.with_mut(|entries| entries.push(Entry { id, callback: Some(Box::new(callback)) }));
+        CallbackHandle { id }
+    }
+
+    /// Remove a callback from the list, so that it is not called again.
+    /// Return whether it was still in the list.
+    pub fn remove(&self, handle: CallbackHandle) -> bool {
+        let entry = self.entries.with_mut(|entries| {
+            entries.binary_search_by_key(&handle.id, |entry| entry.id).ok().map(|index| entries.remove(index))
+        });
+        // Drop the callback outside of `with_mut`, its destructor may access the list.
+        entry.is_some()
+    }
+
+    /// Return the number of callbacks in the list, including running ones.
+    pub fn len(&self) -> usize {
+        self.entries.with_ref(|entries| entries.len())
+    }
+
+    /// Return whether the list has no callbacks.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Call each callback in the list, in the order they were added, with the given arguments.
+    pub fn dispatch(&self, args: &A) {
+        let end = self.next_id.get();
+        let mut next = 0;
+        while let Some((id, callback)) = self.take_next(next, end) {
+            next = id + 1;
+            let mut running = Running { list: self, id, callback: Some(callback) };
+            (running.callback.as_mut().unwrap())(args);
+        }
+    }
+
+    /// Take out the first callback that is not running, with an id in `start..end`.
+    fn take_next(&self, start: u64, end: u64) -> Option<(u64, Callback<A>)> {
+        self.entries.with_mut(|entries| {
+            let index = entries.partition_point(|entry| entry.id < start);
+            entries[index..].iter_mut()
+                .take_while(|entry| entry.id < end)
+                .find_map(|entry| entry.callback.take().map(|callback| (entry.id, callback)))
+        })
+    }
+}
+
+/// Puts a running callback back when it returns or unwinds, unless it was removed.
+struct Running<'a, A: 'a> {
+    list: &'a CallbackList<A>,
+    id: u64,
+    callback: Option<Callback<A>>,
+}
+
+impl<'a, A> Drop for Running<'a, A> {
+    fn drop(&mut self) {
+        let id = self.id;
+        let callback = &mut self.callback;
+        self.list.entries.with_mut(|entries| {
+            if let Ok(index) = entries.binary_search_by_key(&id, |entry| entry.id) {
+                entries[index].callback = callback.take()
+            }
+        });
+    }
+}
+
+impl<A> Default for CallbackList<A> {
+    fn default() -> CallbackList<A> {
+        CallbackList::new()
+    }
+}
+
+impl<A> fmt::Debug for CallbackList<A> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("CallbackList { .. }")
+    }
+}
+
+
+#[test]
+fn callback_list() {
+    use alloc::rc::Rc;
+    use core::cell::RefCell;
+
+    let list = Rc::new(CallbackList::new());
+    let log = Rc::new(RefCell::new(Vec::new()));
+
+    let l = log.clone();
+    let first = list.add(move |&n: &u32| l.borrow_mut().push(("first", n)));
+    assert_eq!(list.len(), 1);
+    list.dispatch(&1);
+    assert_eq!(*log.borrow(), [("first", 1)]);
+    assert!(list.remove(first));
+    assert!(!list.remove(first));
+    assert!(list.is_empty());
+    log.borrow_mut().clear();
+
+    // Adds a callback, removes the third one, and dispatches again, once.
+    let (li, lo) = (Rc::downgrade(&list), log.clone());
+    let third = Rc::new(Cell::new(None));
+    let t = third.clone();
+    list.add(move |&n: &u32| {
+        lo.borrow_mut().push(("reentrant", n));
+        if n == 2 {
+            let list = li.upgrade().unwrap();
+            let l = lo.clone();
+            list.add(move |&n: &u32| l.borrow_mut().push(("added", n)));
+            list.remove(t.get().unwrap());
+            list.dispatch(&3);
+        }
+    });
+    let (l, li) = (log.clone(), Rc::downgrade(&list));
+    let self_removing = Rc::new(Cell::new(None));
+    let s = self_removing.clone();
+    self_removing.set(Some(list.add(move |&n: &u32| {
+        l.borrow_mut().push(("self-removing", n));
+        li.upgrade().unwrap().remove(s.get().unwrap());
+    })));
+    let l = log.clone();
+    third.set(Some(list.add(move |&n: &u32| l.borrow_mut().push(("third", n)))));
+
+    list.dispatch(&2);
+    assert_eq!(*log.borrow(), [
+        ("reentrant", 2),
+        // Nested dispatch: skips the running callback, calls the one added during the outer dispatch.
+        ("self-removing", 3),
+        ("added", 3),
+        // The self-removing callback and the removed third one are not called again.
+    ]);
+    log.borrow_mut().clear();
+
+    list.dispatch(&4);
+    assert_eq!(*log.borrow(), [("reentrant", 4), ("added", 4)]);
+    assert_eq!(list.len(), 2);
+}
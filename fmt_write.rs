@@ -0,0 +1,66 @@
+use core::fmt::{self, Write};
+use MoveCell;
+
+/// Write to the writer in the cell in place.
+///
+/// Fails with `fmt::Error` if the value is already borrowed,
+/// for example if a `Display` impl of a formatted argument writes to the same cell.
+impl<W> Write for &MoveCell<W> where W: Write {
+    #[inline]
+    #[track_caller]
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.try_with_mut(|writer| writer.write_str(s)).map_err(|_| fmt::Error)?
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_char(&mut self, c: char) -> fmt::Result {
+        self.try_with_mut(|writer| writer.write_char(c)).map_err(|_| fmt::Error)?
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_fmt(&mut self, args: fmt::Arguments) -> fmt::Result {
+        self.try_with_mut(|writer| writer.write_fmt(args)).map_err(|_| fmt::Error)?
+    }
+}
+
+
+#[test]
+fn fmt_write() {
+    struct Reentrant<'a>(&'a MoveCell<String>);
+
+    impl<'a> fmt::Display for Reentrant<'a> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(&*self.0, "nested")?;
+            f.write_str("unreachable")
+        }
+    }
+
+    let x = MoveCell::new(String::new());
+    write!(&x, "{}-{}", 1, 2).unwrap();
+    (&x).write_char('!').unwrap();
+    assert_eq!(x.with_ref(|s| s.clone()), "1-2!");
+
+    assert_eq!(write!(&x, "{}", Reentrant(&x)), Err(fmt::Error));
+    assert!(!x.is_borrowed());
+    assert_eq!(x.into_inner(), "1-2!");
+}
+
+#[test]
+#[cfg(feature = "track-borrows")]
+fn fmt_write_location() {
+    struct Probe<'a>(&'a MoveCell<String>);
+
+    impl<'a> fmt::Display for Probe<'a> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let location = self.0.debug_state().location().unwrap();
+            write!(f, "{}:{}", location.file(), location.line())
+        }
+    }
+
+    let x = MoveCell::new(String::new());
+    let line = line!() + 1;
+    write!(&x, "{}", Probe(&x)).unwrap();
+    assert_eq!(x.into_inner(), format!("{}:{}", file!(), line));
+}
@@ -0,0 +1,160 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use {MoveCell, State};
+
+/// Poll the future in the cell in place.
+///
+/// Panics if the value is already borrowed, for example when polled reentrantly,
+/// or if the future already completed.
+impl<F> Future for &MoveCell<F> where F: Future + Unpin {
+    type Output = F::Output;
+
+    #[track_caller]
+    fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<F::Output> {
+        let cell = *self.get_mut();
+        if cell.is_completed() {
+            panic!("MoveCell future polled after completion")
+        }
+        cell.poll_in_place(cx, F::poll, |_| true)
+    }
+}
+
+impl<T> MoveCell<T> where T: Unpin {
+    /// Poll the stream in the cell in place, with a `Stream::poll_next`-style function.
+    ///
+    /// Once the stream ended, this returns `Poll::Ready(None)` without polling it again.
+    ///
+    /// Panics if the value is already borrowed, for example when polled reentrantly.
+    #[track_caller]
+    pub fn poll_next_with<F, Item>(&self, cx: &mut Context, poll_next: F) -> Poll<Option<Item>>
+        where F: FnOnce(Pin<&mut T>, &mut Context) -> Poll<Option<Item>>
+    {
+        if self.is_completed() {
+            return Poll::Ready(None)
+        }
+        self.poll_in_place(cx, poll_next, Option::is_none)
+    }
+
+    #[track_caller]
+    fn poll_in_place<F, R>(&self, cx: &mut Context, poll: F, is_last: fn(&R) -> bool) -> Poll<R>
+        where F: FnOnce(Pin<&mut T>, &mut Context) -> Poll<R>
+    {
+        let result = poll(Pin::new(&mut self.borrow_mut()), cx);
+        if let Poll::Ready(ref output) = result {
+            if is_last(output) {
+                self.state.set(State::Completed)
+            }
+        }
+        result
+    }
+}
+
+
+#[cfg(test)]
+struct Countdown(u32);
+
+#[cfg(test)]
+impl Future for Countdown {
+    type Output = &'static str;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<&'static str> {
+        if self.0 == 0 {
+            return Poll::Ready("done")
+        }
+        self.0 -= 1;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+impl Countdown {
+    fn poll_next(mut self: Pin<&mut Self>, _: &mut Context) -> Poll<Option<u32>> {
+        if self.0 == 0 {
+            return Poll::Ready(None)
+        }
+        self.0 -= 1;
+        Poll::Ready(Some(self.0))
+    }
+}
+
+#[test]
+fn future() {
+    use core::task::Waker;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut cx = Context::from_waker(Waker::noop());
+    let x = MoveCell::new(Countdown(2));
+    assert_eq!(Pin::new(&mut &x).poll(&mut cx), Poll::Pending);
+    assert_eq!(Pin::new(&mut &x).poll(&mut cx), Poll::Pending);
+    assert!(!x.is_completed());
+    assert_eq!(Pin::new(&mut &x).poll(&mut cx), Poll::Ready("done"));
+    assert!(x.is_completed());
+    assert_eq!(x.debug_state().to_string(), "future or stream completed");
+    x.with_ref(|countdown| assert_eq!(countdown.0, 0));
+    assert!(x.is_completed());
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        Pin::new(&mut &x).poll(&mut Context::from_waker(Waker::noop()))
+    }));
+    assert!(result.is_err());
+
+    x.set(Countdown(0));
+    assert!(!x.is_completed());
+    assert_eq!(Pin::new(&mut &x).poll(&mut cx), Poll::Ready("done"));
+}
+
+#[test]
+fn completed_future_lent_in_place() {
+    use core::future::{ready, Ready};
+    use core::task::Waker;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    // Like an `async` block, `Ready` panics if it is polled again after completion.
+    let x = MoveCell::new(Box::pin(ready(1)));
+    let poll = |x: &MoveCell<Pin<Box<Ready<u32>>>>| {
+        Pin::new(&mut &*x).poll(&mut Context::from_waker(Waker::noop()))
+    };
+    assert_eq!(poll(&x), Poll::Ready(1));
+    x.with_mut(|_| ());
+    assert!(x.is_completed());
+    drop(x.borrow_mut());
+    assert!(x.is_completed());
+    drop(x.borrow_with(|| Box::pin(ready(2))));
+    assert!(x.is_completed());
+
+    let result = catch_unwind(AssertUnwindSafe(|| poll(&x)));
+    let message = result.unwrap_err().downcast::<&str>().unwrap();
+    assert_eq!(*message, "MoveCell future polled after completion");
+
+    x.set(Box::pin(ready(3)));
+    assert_eq!(poll(&x), Poll::Ready(3));
+    x.update(|_| Box::pin(ready(4)));
+    assert!(!x.is_completed());
+}
+
+#[test]
+fn stream() {
+    use core::task::Waker;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut cx = Context::from_waker(Waker::noop());
+    let x = MoveCell::new(Countdown(2));
+    assert_eq!(x.poll_next_with(&mut cx, Countdown::poll_next), Poll::Ready(Some(1)));
+    assert_eq!(x.poll_next_with(&mut cx, Countdown::poll_next), Poll::Ready(Some(0)));
+    assert_eq!(x.poll_next_with(&mut cx, Countdown::poll_next), Poll::Ready(None));
+    assert!(x.is_completed());
+    assert_eq!(x.poll_next_with(&mut cx, |_, _| -> Poll<Option<u32>> { unreachable!() }), Poll::Ready(None));
+
+    let y = MoveCell::new(Countdown(1));
+    let reentrant = y.poll_next_with(&mut cx, |countdown, cx| {
+        assert!(y.is_borrowed());
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            y.poll_next_with(cx, Countdown::poll_next)
+        }));
+        assert!(result.is_err());
+        countdown.poll_next(cx)
+    });
+    assert_eq!(reentrant, Poll::Ready(Some(0)));
+}
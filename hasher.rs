@@ -0,0 +1,114 @@
+use core::hash::Hasher;
+use MoveCell;
+
+/// Feed the hasher in the cell in place.
+///
+/// Panics if the value is already borrowed,
+/// for example if a `Hash` impl accesses the same cell.
+impl<H> Hasher for &MoveCell<H> where H: Hasher {
+    #[inline]
+    #[track_caller]
+    fn finish(&self) -> u64 {
+        self.with_ref(|hasher| hasher.finish())
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write(&mut self, bytes: &[u8]) {
+        self.with_mut(|hasher| hasher.write(bytes))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_u8(&mut self, i: u8) {
+        self.with_mut(|hasher| hasher.write_u8(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_u16(&mut self, i: u16) {
+        self.with_mut(|hasher| hasher.write_u16(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_u32(&mut self, i: u32) {
+        self.with_mut(|hasher| hasher.write_u32(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_u64(&mut self, i: u64) {
+        self.with_mut(|hasher| hasher.write_u64(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_u128(&mut self, i: u128) {
+        self.with_mut(|hasher| hasher.write_u128(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_usize(&mut self, i: usize) {
+        self.with_mut(|hasher| hasher.write_usize(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_i8(&mut self, i: i8) {
+        self.with_mut(|hasher| hasher.write_i8(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_i16(&mut self, i: i16) {
+        self.with_mut(|hasher| hasher.write_i16(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_i32(&mut self, i: i32) {
+        self.with_mut(|hasher| hasher.write_i32(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_i64(&mut self, i: i64) {
+        self.with_mut(|hasher| hasher.write_i64(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_i128(&mut self, i: i128) {
+        self.with_mut(|hasher| hasher.write_i128(i))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn write_isize(&mut self, i: isize) {
+        self.with_mut(|hasher| hasher.write_isize(i))
+    }
+}
+
+
+#[test]
+fn hasher() {
+    use core::hash::Hash;
+    use std::collections::hash_map::DefaultHasher;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let mut expected = DefaultHasher::new();
+    "first".hash(&mut expected);
+    42_u32.hash(&mut expected);
+
+    let x = MoveCell::new(DefaultHasher::new());
+    "first".hash(&mut &x);
+    42_u32.hash(&mut &x);
+    assert_eq!((&x).finish(), expected.finish());
+
+    let reentrant = catch_unwind(AssertUnwindSafe(|| {
+        x.with_mut(|_| 1_u8.hash(&mut &x))
+    }));
+    assert!(reentrant.is_err());
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+use std::io::{self, BufRead, IoSlice, IoSliceMut, Read, Seek, SeekFrom, Write};
+use {BorrowError, BorrowMut, MoveCell};
+
+// `BufRead` is not implemented for `&MoveCell<T>`: `fill_buf` returns a slice of the reader’s buffer
+// that would outlive the borrow of the cell. It is implemented for `BorrowMut` instead.
+
+fn to_io_error(error: BorrowError) -> io::Error {
+    io::Error::other(error)
+}
+
+/// Read from the reader in the cell in place.
+///
+/// Fails with an `io::Error` if the value is already borrowed.
+impl<T> Read for &MoveCell<T> where T: Read {
+    #[track_caller]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.try_with_mut(|reader| reader.read(buf)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        self.try_with_mut(|reader| reader.read_vectored(bufs)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        self.try_with_mut(|reader| reader.read_to_end(buf)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
+        self.try_with_mut(|reader| reader.read_to_string(buf)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        self.try_with_mut(|reader| reader.read_exact(buf)).map_err(to_io_error)?
+    }
+}
+
+/// Write to the writer in the cell in place.
+///
+/// Fails with an `io::Error` if the value is already borrowed,
+/// for example if a `Display` impl of a formatted argument writes to the same cell.
+impl<T> Write for &MoveCell<T> where T: Write {
+    #[track_caller]
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.try_with_mut(|writer| writer.write(buf)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn write_vectored(&mut self, bufs: &[IoSlice]) -> io::Result<usize> {
+        self.try_with_mut(|writer| writer.write_vectored(bufs)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn flush(&mut self) -> io::Result<()> {
+        self.try_with_mut(|writer| writer.flush()).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.try_with_mut(|writer| writer.write_all(buf)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn write_fmt(&mut self, args: fmt::Arguments) -> io::Result<()> {
+        self.try_with_mut(|writer| writer.write_fmt(args)).map_err(to_io_error)?
+    }
+}
+
+/// Seek in the value in the cell in place.
+///
+/// Fails with an `io::Error` if the value is already borrowed.
+impl<T> Seek for &MoveCell<T> where T: Seek {
+    #[track_caller]
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.try_with_mut(|seeker| seeker.seek(pos)).map_err(to_io_error)?
+    }
+
+    #[track_caller]
+    fn stream_position(&mut self) -> io::Result<u64> {
+        self.try_with_mut(|seeker| seeker.stream_position()).map_err(to_io_error)?
+    }
+}
+
+impl<'a, T> Read for BorrowMut<'a, T> where T: Read {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (**self).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [IoSliceMut]) -> io::Result<usize> {
+        (**self).read_vectored(bufs)
+    }
+}
+
+impl<'a, T> BufRead for BorrowMut<'a, T> where T: BufRead {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        (**self).fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        (**self).consume(amt)
+    }
+}
+
+
+#[test]
+fn write() {
+    let x = MoveCell::new(Vec::new());
+    write!(&x, "{}-{}", 1, 2).unwrap();
+    (&x).write_all(b"!").unwrap();
+    (&x).flush().unwrap();
+
+    x.with_mut(|v| {
+        let error = (&x).write(b"nested").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::Other);
+        if cfg!(not(feature = "track-borrows")) {
+            assert_eq!(error.to_string(), "MoveCell value already borrowed");
+        }
+        assert_eq!(v, b"1-2!");
+    });
+}
+
+#[test]
+fn read_and_seek() {
+    use std::io::Cursor;
+
+    let x = MoveCell::new(Cursor::new(b"first\nsecond\n".to_vec()));
+    let mut buf = [0; 5];
+    (&x).read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"first");
+    assert_eq!((&x).stream_position().unwrap(), 5);
+    (&x).seek(SeekFrom::Start(6)).unwrap();
+
+    let mut line = String::new();
+    x.borrow_mut().read_line(&mut line).unwrap();
+    assert_eq!(line, "second\n");
+    assert_eq!((&x).read(&mut buf).unwrap(), 0);
+
+    let _guard = x.borrow_mut();
+    assert!((&x).read(&mut buf).is_err());
+}
+
+#[test]
+#[cfg(feature = "track-borrows")]
+fn write_location() {
+    struct Probe<'a>(&'a MoveCell<Vec<u8>>);
+
+    impl<'a> fmt::Display for Probe<'a> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            let location = self.0.debug_state().location().unwrap();
+            write!(f, "{}:{}", location.file(), location.line())
+        }
+    }
+
+    let x = MoveCell::new(Vec::new());
+    let line = line!() + 1;
+    write!(&x, "{}", Probe(&x)).unwrap();
+    let error = x.with_mut(|_| (&x).write(b"nested").unwrap_err());
+    assert_eq!(x.into_inner(), format!("{}:{}", file!(), line).into_bytes());
+    let error = error.get_ref().unwrap().downcast_ref::<BorrowError>().unwrap();
+    assert_eq!(error.location().unwrap().line(), line + 1);
+}
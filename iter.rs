@@ -0,0 +1,88 @@
+use MoveCell;
+
+/// Advance the iterator in the cell in place.
+///
+/// Panics if the value is already borrowed,
+/// for example if the iterator accesses the same cell.
+impl<I> Iterator for &MoveCell<I> where I: Iterator {
+    type Item = I::Item;
+
+    #[inline]
+    #[track_caller]
+    fn next(&mut self) -> Option<I::Item> {
+        self.with_mut(|iter| iter.next())
+    }
+
+    #[inline]
+    #[track_caller]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.with_ref(|iter| iter.size_hint())
+    }
+
+    #[inline]
+    #[track_caller]
+    fn nth(&mut self, n: usize) -> Option<I::Item> {
+        self.with_mut(|iter| iter.nth(n))
+    }
+}
+
+/// Extend the collection in the cell in place.
+///
+/// Panics if the value is already borrowed,
+/// for example if the iterator accesses the same cell.
+impl<A, C> Extend<A> for &MoveCell<C> where C: Extend<A> {
+    #[inline]
+    #[track_caller]
+    fn extend<I>(&mut self, iter: I) where I: IntoIterator<Item = A> {
+        self.with_mut(|collection| collection.extend(iter))
+    }
+}
+
+
+#[test]
+fn iterator() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let x = MoveCell::new(1..5);
+    assert_eq!((&x).size_hint(), (4, Some(4)));
+    assert_eq!((&x).next(), Some(1));
+    assert_eq!((&x).nth(1), Some(3));
+    let rest: Vec<_> = (&x).collect();
+    assert_eq!(rest, [4]);
+    assert_eq!((&x).next(), None);
+
+    let tokens = MoveCell::new(vec!["a", "b", "c"].into_iter());
+    let mut seen = Vec::new();
+    for token in &tokens {
+        seen.push(token);
+        if token == "a" {
+            assert_eq!((&tokens).next(), Some("b"));
+        }
+    }
+    assert_eq!(seen, ["a", "c"]);
+
+    let z = MoveCell::new((0..3).map(|i| i * 2));
+    let reentrant = catch_unwind(AssertUnwindSafe(|| {
+        z.with_mut(|_| (&z).next())
+    }));
+    assert!(reentrant.is_err());
+}
+
+#[test]
+fn extend() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let x = MoveCell::new(Vec::new());
+    let visitors = [1, 2, 3];
+    for &visitor in &visitors {
+        (&x).extend(Some(visitor));
+    }
+    (&x).extend(vec![4, 5]);
+    assert_eq!(x.into_inner(), [1, 2, 3, 4, 5]);
+
+    let y = MoveCell::new(vec![1]);
+    let reentrant = catch_unwind(AssertUnwindSafe(|| {
+        (&y).extend((0..1).map(|_| y.with_ref(|v| v.len())))
+    }));
+    assert!(reentrant.is_err());
+}
@@ -1,43 +1,608 @@
-use std::cell::UnsafeCell;
-use std::fmt;
-use std::mem;
-use std::ops;
-use std::ptr;
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(any(feature = "std", test))] extern crate core;
+#[cfg(feature = "alloc")] extern crate alloc;
+#[cfg(feature = "serde")] extern crate serde;
+#[cfg(all(test, feature = "serde"))] extern crate serde_test;
+
+use core::cell::{Cell, UnsafeCell};
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::mem::{self, ManuallyDrop};
+use core::ops;
+use core::panic::Location;
+use core::ptr;
+
+#[cfg(feature = "alloc")] pub use async_cell::{AsyncBorrow, AsyncMoveCell, BorrowFuture, Take};
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))] pub use atomic::{AtomicMoveCell, AtomicPointer};
+#[cfg(feature = "alloc")] pub use callback_list::{CallbackHandle, CallbackList};
+#[cfg(feature = "std")] pub use local_key::LocalKeyExt;
+pub use plain::PlainMoveCell;
+#[cfg(feature = "std")] pub use slot::{Slot, SlotReceiver, SlotSender};
+#[cfg(target_has_atomic = "8")] pub use sync::{SyncBorrow, SyncMoveCell};
+#[cfg(feature = "std")] pub use thread_bound::{ThreadBoundCell, WrongThread};
+#[cfg(feature = "alloc")] pub use work_queue::{DrainError, QueueOrder, WorkQueue};
+
+#[cfg(feature = "alloc")] mod async_cell;
+#[cfg(all(feature = "alloc", target_has_atomic = "ptr"))] mod atomic;
+mod call;
+#[cfg(feature = "alloc")] mod callback_list;
+mod fmt_write;
+mod future;
+mod hasher;
+#[cfg(feature = "std")] mod io;
+mod iter;
+#[cfg(feature = "std")] mod local_key;
+mod plain;
+#[cfg(feature = "serde")] mod serde_impls;
+#[cfg(feature = "std")] mod slot;
+#[cfg(target_has_atomic = "8")] mod sync;
+#[cfg(feature = "std")] mod thread_bound;
+#[cfg(feature = "alloc")] mod work_queue;
 
 /// A container similar to [`std::cell::Cell`](http://doc.rust-lang.org/std/cell/struct.Cell.html),
 /// but that also supports not-implicitly-copyable types.
-pub struct MoveCell<T>(UnsafeCell<T>);
+///
+/// The borrow state is stored next to the value, so `MoveCell<T>` does not have the layout of `T`.
+/// See `PlainMoveCell` for views of `&mut T` or `&Cell<T>` as a cell.
+pub struct MoveCell<T> {
+    value: UnsafeCell<T>,
+    state: Cell<State>,
+    /// Where the value was last moved out, if it hasn’t been put back since.
+    #[cfg(feature = "track-borrows")]
+    moved_out_at: Cell<Option<&'static Location<'static>>>,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+enum State {
+    /// The cell contains a value.
+    Available,
+    /// The value was moved out by a `Borrow` guard, the cell contains a placeholder.
+    Lent,
+    /// Like `Lent`, but something was written to the cell in the meantime.
+    Overwritten,
+    /// The value was moved out by `update`, the cell is logically empty.
+    Updating,
+    /// The value is lent in place by `with_mut` or a `BorrowMut` guard.
+    InPlace,
+    /// The value is lent by shared reference, this many times, by `with_ref`.
+    Reading(usize),
+    /// An update function panicked, the cell contains a placeholder for good.
+    Poisoned,
+    /// The cell contains a future or stream that completed, and that must not be polled again.
+    /// Otherwise like `Available`.
+    Completed,
+}
+
+impl State {
+    #[inline]
+    fn is_borrowed(self) -> bool {
+        match self {
+            State::Lent | State::Overwritten | State::Updating | State::InPlace |
+            State::Reading(_) => true,
+            State::Available | State::Completed | State::Poisoned => false,
+        }
+    }
+}
+
+/// What `MoveCell::update_with_policy` does with the cell if the update function panics.
+pub enum OnPanic<T> {
+    /// Abort the process.
+    Abort,
+    /// Put the given value in the cell.
+    Restore(T),
+    /// Put the given placeholder value in the cell and mark it as poisoned.
+    /// Any later access to it panics or returns an error.
+    Poison(T),
+}
+
+impl<T: Placeholder> OnPanic<T> {
+    /// Poison the cell, leaving a placeholder value in it.
+    #[inline]
+    pub fn poison() -> OnPanic<T> {
+        OnPanic::Poison(T::placeholder())
+    }
+}
 
 
 impl<T> MoveCell<T> {
     /// Create a new `MoveCell` containing the given value.
+    ///
+    /// This can be used in constant expressions, such as `thread_local!` initializers.
     #[inline]
-    pub fn new(value: T) -> MoveCell<T> {
-        MoveCell(UnsafeCell::new(value))
+    pub const fn new(value: T) -> MoveCell<T> {
+        MoveCell {
+            value: UnsafeCell::new(value),
+            state: Cell::new(State::Available),
+            #[cfg(feature = "track-borrows")]
+            moved_out_at: Cell::new(None),
+        }
     }
 
     /// Consume the `MoveCell` and return the inner value.
+    ///
+    /// If a `Borrow` guard was leaked with `mem::forget`, this returns the placeholder.
+    ///
+    /// Panics if the cell is poisoned.
     #[inline]
+    #[track_caller]
     pub fn into_inner(self) -> T {
-        unsafe { self.0.into_inner() }
+        self.check_not_poisoned();
+        self.value.into_inner()
     }
 
     /// Return the inner value after replacing it with the given value.
+    ///
+    /// While the value is lent out by a `Borrow` guard,
+    /// this returns the placeholder that the cell contains in the meantime
+    /// (or the previously written value)
+    /// and the guard’s `OnConflict` policy applies when the guard is dropped.
+    ///
+    /// Panics if the value is being updated or lent in place, or if the cell is poisoned.
     #[inline]
+    #[track_caller]
     pub fn replace(&self, new_value: T) -> T {
+        match self.state.get() {
+            State::Lent | State::Overwritten => self.state.set(State::Overwritten),
+            _ => {
+                self.check_available();
+                self.state.set(State::Available);
+                self.set_moved_out_at(None)
+            }
+        }
+        unsafe {
+            mem::replace(&mut *self.value.get(), new_value)
+        }
+    }
+
+    /// Set the inner value, dropping the previous one.
+    ///
+    /// This behaves like `replace`, see its panics.
+    #[inline]
+    #[track_caller]
+    pub fn set(&self, value: T) {
+        drop(self.replace(value))
+    }
+
+    /// Swap the values of two cells.
+    ///
+    /// Panics if either value is borrowed, or if either cell is poisoned.
+    #[inline]
+    #[track_caller]
+    pub fn swap(&self, other: &MoveCell<T>) {
+        if ptr::eq(self, other) {
+            return
+        }
+        self.check_available();
+        other.check_available();
+        self.set_moved_out_at(None);
+        other.set_moved_out_at(None);
+        let state = self.state.replace(other.state.get());
+        other.state.set(state);
         unsafe {
-            mem::replace(&mut *self.0.get(), new_value)
+            ptr::swap(self.value.get(), other.value.get())
+        }
+    }
+
+    /// Return a mutable reference to the inner value.
+    ///
+    /// This is statically guaranteed not to conflict with any other access,
+    /// so this also ends any borrow state left behind by a guard leaked with `mem::forget`.
+    ///
+    /// Panics if the cell is poisoned.
+    #[inline]
+    #[track_caller]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.check_not_poisoned();
+        self.state.set(State::Available);
+        self.set_moved_out_at(None);
+        self.value.get_mut()
+    }
+
+    /// Return a `Cell` view of the inner value, for code written against `Cell`.
+    ///
+    /// Panics if the cell is poisoned.
+    #[inline]
+    #[track_caller]
+    pub fn as_cell(&mut self) -> &Cell<T> {
+        Cell::from_mut(self.get_mut())
+    }
+
+    /// Create a new `MoveCell` containing the value of the given `Cell`.
+    #[inline]
+    pub fn from_cell(cell: Cell<T>) -> MoveCell<T> {
+        MoveCell::new(cell.into_inner())
+    }
+
+    /// Consume the `MoveCell` and return a `Cell` containing the inner value.
+    ///
+    /// Panics if the cell is poisoned.
+    #[inline]
+    #[track_caller]
+    pub fn into_cell(self) -> Cell<T> {
+        Cell::new(self.into_inner())
+    }
+
+    /// Replace the inner value with the result of calling `f` with the current value.
+    ///
+    /// This does not require a placeholder value.
+    /// If `f` panics, the process aborts.
+    /// See `update_with_policy` for other behaviors.
+    #[inline]
+    #[track_caller]
+    pub fn update<F>(&self, f: F) where F: FnOnce(T) -> T {
+        self.update_with_policy(OnPanic::Abort, |value| (f(value), ()))
+    }
+
+    /// Replace the inner value with the first item returned by `f`,
+    /// and return the second.
+    ///
+    /// This does not require a placeholder value.
+    /// If `f` panics, the process aborts.
+    /// See `update_with_policy` for other behaviors.
+    #[inline]
+    #[track_caller]
+    pub fn update_with<F, R>(&self, f: F) -> R where F: FnOnce(T) -> (T, R) {
+        self.update_with_policy(OnPanic::Abort, f)
+    }
+
+    /// Move the inner value into `f`, store the first item it returns in the cell,
+    /// and return the second.
+    ///
+    /// While `f` runs the cell is empty, and any access to it panics.
+    /// If `f` panics, `on_panic` decides what happens to the cell
+    /// before unwinding continues.
+    ///
+    /// Panics if the value is currently borrowed, or if the cell is poisoned.
+    #[track_caller]
+    pub fn update_with_policy<F, R>(&self, on_panic: OnPanic<T>, f: F) -> R
+    where F: FnOnce(T) -> (T, R) {
+        self.update_at(on_panic, f, Location::caller())
+    }
+
+    /// Like `update_with_policy`, recording `location` as where the value was moved out.
+    #[track_caller]
+    fn update_at<F, R>(&self, on_panic: OnPanic<T>, f: F, location: &'static Location<'static>) -> R
+    where F: FnOnce(T) -> (T, R) {
+        self.check_available();
+        self.state.set(State::Updating);
+        self.set_moved_out_at(Some(location));
+        // Until the guard is forgotten or dropped, the cell holds a bitwise copy of `value`.
+        let value = unsafe { ptr::read(self.value.get()) };
+        let guard = UpdateGuard {
+            cell: self,
+            on_panic,
+        };
+        let (new_value, result) = f(value);
+        let on_panic = unsafe { ptr::read(&guard.on_panic) };
+        mem::forget(guard);
+        unsafe {
+            ptr::write(self.value.get(), new_value)
+        }
+        self.state.set(State::Available);
+        self.set_moved_out_at(None);
+        drop(on_panic);
+        result
+    }
+
+    /// Lend the inner value to `f` in place, without moving it
+    /// and without requiring a placeholder value.
+    ///
+    /// While `f` runs, any other access to the cell panics
+    /// or returns an error.
+    ///
+    /// Panics if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn with_mut<F, R>(&self, f: F) -> R where F: FnOnce(&mut T) -> R {
+        self.with_mut_at(f, Location::caller())
+    }
+
+    /// Like `with_mut`, recording `location` as where the value was lent.
+    #[inline]
+    #[track_caller]
+    fn with_mut_at<F, R>(&self, f: F, location: &'static Location<'static>) -> R
+    where F: FnOnce(&mut T) -> R {
+        match self.try_borrow_mut_at(location) {
+            Ok(mut guard) => f(&mut guard),
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `with_mut`, but return an error instead of panicking
+    /// if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn try_with_mut<F, R>(&self, f: F) -> Result<R, BorrowError> where F: FnOnce(&mut T) -> R {
+        let mut guard = self.try_borrow_mut()?;
+        Ok(f(&mut guard))
+    }
+
+    /// Return a `BorrowMut` guard giving access to the inner value in place.
+    /// Until the guard is dropped, any other access to the cell panics
+    /// or returns an error.
+    ///
+    /// Unlike `borrow`, this does not move the value and does not require a placeholder.
+    ///
+    /// Panics if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn borrow_mut(&self) -> BorrowMut<'_, T> {
+        match self.try_borrow_mut() {
+            Ok(borrow) => borrow,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `borrow_mut`, but return an error instead of panicking
+    /// if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn try_borrow_mut(&self) -> Result<BorrowMut<'_, T>, BorrowError> {
+        self.try_borrow_mut_at(Location::caller())
+    }
+
+    #[inline]
+    fn try_borrow_mut_at(&self, location: &'static Location<'static>)
+                         -> Result<BorrowMut<'_, T>, BorrowError> {
+        self.check_unborrowed()?;
+        let restore = self.state.replace(State::InPlace);
+        let restore_location = self.moved_out_at();
+        self.set_moved_out_at(Some(location));
+        Ok(BorrowMut {
+            _cell: self,
+            _restore: restore,
+            _restore_location: restore_location,
+        })
+    }
+
+    /// Lend a shared reference to the inner value to `f`,
+    /// without moving it and without requiring a placeholder value.
+    ///
+    /// While `f` runs, the cell can be read again with `with_ref`
+    /// but any other access panics or returns an error.
+    ///
+    /// Panics if the value is moved out or mutably borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn with_ref<F, R>(&self, f: F) -> R where F: FnOnce(&T) -> R {
+        self.with_ref_at(f, Location::caller())
+    }
+
+    /// Like `with_ref`, recording `location` as where the value was lent.
+    #[inline]
+    #[track_caller]
+    fn with_ref_at<F, R>(&self, f: F, location: &'static Location<'static>) -> R
+    where F: FnOnce(&T) -> R {
+        match self.try_with_ref_at(f, location) {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `with_ref`, but return an error instead of panicking
+    /// if the value is moved out or mutably borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn try_with_ref<F, R>(&self, f: F) -> Result<R, BorrowError> where F: FnOnce(&T) -> R {
+        self.try_with_ref_at(f, Location::caller())
+    }
+
+    #[inline]
+    fn try_with_ref_at<F, R>(&self, f: F, location: &'static Location<'static>)
+                             -> Result<R, BorrowError>
+    where F: FnOnce(&T) -> R {
+        let state = self.state.get();
+        let readers = match state {
+            State::Reading(readers) => readers,
+            _ => {
+                self.check_unborrowed()?;
+                0
+            }
+        };
+        let restore_location = self.moved_out_at();
+        if readers == 0 {
+            self.set_moved_out_at(Some(location));
         }
+        self.state.set(State::Reading(readers + 1));
+        let _guard = ReadGuard { cell: self, restore: state, restore_location };
+        Ok(f(unsafe { &*self.value.get() }))
+    }
+
+    /// Return whether the value is currently moved out or lent,
+    /// by a `Borrow` or `BorrowMut` guard, `with_ref`, `with_mut` or during `update`.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        self.state.get().is_borrowed()
+    }
+
+    /// Return whether the future or stream in this cell completed.
+    /// It will not be polled again, until a new value is written to the cell
+    /// by `replace`, `set`, `swap`, `update` or `take`.
+    /// Lending the value with `borrow`, `borrow_mut` or `with_mut` keeps it completed.
+    #[inline]
+    pub fn is_completed(&self) -> bool {
+        self.state.get() == State::Completed
+    }
+
+    /// Return whether an update function panicked and poisoned this cell.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.state.get() == State::Poisoned
+    }
+
+    /// Return a description of the cell’s current state, for debugging.
+    ///
+    /// With the `track-borrows` feature, this includes where the value was moved out
+    /// by `take`, `borrow` or `update`, or lent by `borrow_mut`, `with_mut` or `with_ref`.
+    pub fn debug_state(&self) -> DebugState {
+        DebugState {
+            state: self.state.get(),
+            location: self.moved_out_at(),
+        }
+    }
+
+    /// Returns a raw pointer to the inner value.
+    ///
+    /// The same restrictions as for `as_unsafe_cell` apply to accesses through this pointer.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut T {
+        self.value.get()
     }
 
     /// Returns a reference to the underlying `UnsafeCell`.
     ///
-    /// ## Unsafety
+    /// # Safety
     ///
     /// This method is unsafe because `UnsafeCell`'s field is public.
+    /// The value must not be accessed through it while the cell is being updated.
     #[inline]
     pub unsafe fn as_unsafe_cell(&self) -> &UnsafeCell<T> {
-        &self.0
+        &self.value
+    }
+
+    #[inline]
+    #[track_caller]
+    fn check_not_poisoned(&self) {
+        if self.is_poisoned() {
+            panic!("{}", BorrowError { location: None, poisoned: true })
+        }
+    }
+
+    #[inline]
+    #[track_caller]
+    fn check_available(&self) {
+        if let Err(error) = self.check_unborrowed() {
+            panic!("{}", error)
+        }
+    }
+
+    /// Put back a value that was lent out by a `Borrow` guard,
+    /// with the state it had before if the guard’s value ends up in the cell.
+    /// Return whether the cell was written to in the meantime.
+    fn give_back(&self, value: T, on_conflict: &OnConflict<T>, restore: State) -> bool {
+        let conflict = match self.state.get() {
+            State::Lent => false,
+            State::Overwritten => true,
+            state => unreachable!("unexpected {:?} state with a Borrow guard", state),
+        };
+        self.state.set(State::Available);
+        self.set_moved_out_at(None);
+        if !conflict {
+            self.replace(value);
+            self.state.set(restore);
+            return false
+        }
+        match *on_conflict {
+            OnConflict::Panic => {
+                drop(value);
+                if !panicking() {
+                    panic!("MoveCell written to while its value was borrowed")
+                }
+            }
+            OnConflict::KeepNewer => drop(value),
+            OnConflict::KeepBorrowed => {
+                drop(self.replace(value));
+                self.state.set(restore)
+            }
+            OnConflict::Merge(merge) => self.update(|newer| merge(value, newer)),
+        }
+        true
+    }
+
+    #[inline]
+    #[track_caller]
+    fn check_unborrowed(&self) -> Result<(), BorrowError> {
+        match self.state.get() {
+            State::Available | State::Completed => Ok(()),
+            State::Lent | State::Overwritten | State::Updating | State::InPlace |
+            State::Reading(_) => Err(BorrowError {
+                location: self.moved_out_at(),
+                poisoned: false,
+            }),
+            State::Poisoned => Err(BorrowError {
+                location: None,
+                poisoned: true,
+            }),
+        }
+    }
+
+    #[inline]
+    fn moved_out_at(&self) -> Option<&'static Location<'static>> {
+        #[cfg(feature = "track-borrows")]
+        return self.moved_out_at.get();
+        #[cfg(not(feature = "track-borrows"))]
+        return None;
+    }
+
+    #[inline]
+    fn set_moved_out_at(&self, _location: Option<&'static Location<'static>>) {
+        #[cfg(feature = "track-borrows")]
+        self.moved_out_at.set(_location)
+    }
+}
+
+/// Ends a `with_ref` read, including when its function unwinds.
+struct ReadGuard<'a, T: 'a> {
+    cell: &'a MoveCell<T>,
+    /// The state and location before the read started, restored by the outermost reader.
+    restore: State,
+    restore_location: Option<&'static Location<'static>>,
+}
+
+impl<'a, T> Drop for ReadGuard<'a, T> {
+    fn drop(&mut self) {
+        self.cell.state.set(match self.cell.state.get() {
+            State::Reading(1) => {
+                self.cell.set_moved_out_at(self.restore_location);
+                self.restore
+            }
+            State::Reading(readers) => State::Reading(readers - 1),
+            state => unreachable!("unexpected {:?} state with a reader", state),
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+fn panicking() -> bool {
+    std::thread::panicking()
+}
+
+#[cfg(not(feature = "std"))]
+fn panicking() -> bool {
+    false
+}
+
+#[cfg(feature = "std")]
+fn abort() -> ! {
+    std::process::abort()
+}
+
+/// Only called while unwinding, when panicking again aborts.
+#[cfg(not(feature = "std"))]
+fn abort() -> ! {
+    panic!("MoveCell update function panicked with OnPanic::Abort")
+}
+
+/// Applies the panic policy when an update function unwinds,
+/// overwriting the bitwise copy of the moved-out value.
+struct UpdateGuard<'a, T: 'a> {
+    cell: &'a MoveCell<T>,
+    on_panic: OnPanic<T>,
+}
+
+impl<'a, T> Drop for UpdateGuard<'a, T> {
+    fn drop(&mut self) {
+        let (value, state) = match mem::replace(&mut self.on_panic, OnPanic::Abort) {
+            OnPanic::Abort => abort(),
+            OnPanic::Restore(value) => (value, State::Available),
+            OnPanic::Poison(placeholder) => (placeholder, State::Poisoned),
+        };
+        unsafe {
+            ptr::write(self.cell.value.get(), value)
+        }
+        self.cell.state.set(state)
     }
 }
 
@@ -48,54 +613,343 @@ impl<T: Default> Default for MoveCell<T> {
     }
 }
 
-/// Convenience methods for when there is a default value.
-impl<T: Default> MoveCell<T> {
-    /// Return the inner value after replacing it with the default value.
+impl<T> From<T> for MoveCell<T> {
+    #[inline]
+    fn from(value: T) -> MoveCell<T> {
+        MoveCell::new(value)
+    }
+}
+
+impl<T: Copy> MoveCell<T> {
+    /// Return a copy of the inner value.
+    ///
+    /// Panics if the value is moved out or mutably borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn get(&self) -> T {
+        self.with_ref(|value| *value)
+    }
+}
+
+/// A cheap stand-in value that a `MoveCell` contains
+/// while its value is moved out by `take` or `borrow`.
+///
+/// This is implemented for every type that implements `Default`.
+/// Other types can implement it directly.
+pub trait Placeholder {
+    /// Return a placeholder value.
+    fn placeholder() -> Self;
+}
+
+impl<T: Default> Placeholder for T {
+    #[inline]
+    fn placeholder() -> T {
+        T::default()
+    }
+}
+
+/// Methods that move the value out and leave a placeholder in the cell.
+impl<T> MoveCell<T> {
+    /// Return the inner value after replacing it with the result of `placeholder`.
+    ///
+    /// Panics if the value is currently borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn take_with<F>(&self, placeholder: F) -> T where F: FnOnce() -> T {
+        match self.try_take_with(placeholder) {
+            Ok(value) => value,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `borrow`, with the cell containing the result of `placeholder`
+    /// until the guard is dropped.
+    ///
+    /// Panics if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn borrow_with<F>(&self, placeholder: F) -> Borrow<'_, T> where F: FnOnce() -> T {
+        match self.try_borrow_with_policy(placeholder, OnConflict::KeepBorrowed) {
+            Ok(borrow) => borrow,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `borrow_with`, but return an error instead of panicking
+    /// if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn try_borrow_with<F>(&self, placeholder: F) -> Result<Borrow<'_, T>, BorrowError>
+    where F: FnOnce() -> T {
+        self.try_borrow_with_policy(placeholder, OnConflict::KeepBorrowed)
+    }
+
+    /// Like `borrow_with`, with `on_conflict` deciding what happens
+    /// if the cell is written to before the guard returns its value.
+    #[inline]
+    #[track_caller]
+    pub fn borrow_with_policy_and<'a, F>(&'a self, placeholder: F, on_conflict: OnConflict<'a, T>)
+                                         -> Borrow<'a, T>
+    where F: FnOnce() -> T {
+        match self.try_borrow_with_policy(placeholder, on_conflict) {
+            Ok(borrow) => borrow,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `take_with`, but return an error instead of panicking
+    /// if the value is currently borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn try_take_with<F>(&self, placeholder: F) -> Result<T, BorrowError> where F: FnOnce() -> T {
+        self.try_take_with_at(placeholder, Location::caller())
+    }
+
+    #[inline]
+    fn try_take_with_at<F>(&self, placeholder: F, location: &'static Location<'static>)
+                           -> Result<T, BorrowError>
+    where F: FnOnce() -> T {
+        self.check_unborrowed()?;
+        let value = self.replace(placeholder());
+        self.set_moved_out_at(Some(location));
+        Ok(value)
+    }
+
+    #[inline]
+    #[track_caller]
+    fn try_borrow_with_policy<'a, F>(&'a self, placeholder: F, on_conflict: OnConflict<'a, T>)
+                                     -> Result<Borrow<'a, T>, BorrowError>
+    where F: FnOnce() -> T {
+        let restore = self.state.get();
+        let value = self.try_take_with(placeholder)?;
+        self.state.set(State::Lent);
+        Ok(Borrow {
+            _cell: self,
+            _value: ManuallyDrop::new(value),
+            _on_conflict: on_conflict,
+            _restore: restore,
+        })
+    }
+}
+
+/// Convenience methods for when there is a placeholder value.
+impl<T: Placeholder> MoveCell<T> {
+    /// Return the inner value after replacing it with a placeholder value.
+    ///
+    /// Panics if the value is currently borrowed.
     #[inline]
+    #[track_caller]
     pub fn take(&self) -> T {
-        self.replace(T::default())
+        self.take_at(Location::caller())
+    }
+
+    /// Like `take`, recording `location` as where the value was moved out.
+    #[inline]
+    #[track_caller]
+    fn take_at(&self, location: &'static Location<'static>) -> T {
+        match self.try_take_with_at(T::placeholder, location) {
+            Ok(value) => value,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Return the inner value after replacing it with a placeholder value,
+    /// or an error if the value is currently borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn try_take(&self) -> Result<T, BorrowError> {
+        self.try_take_with(T::placeholder)
     }
 
     /// Take the value, and return it in a `Borrow` guard that will return it when dropped.
-    /// The cell’s contents are set to the default value until the guard is dropped.
+    /// The cell’s contents are set to a placeholder value until the guard is dropped.
+    ///
+    /// If the cell is written to in the meantime, the newer value is dropped
+    /// when the guard returns its own. See `borrow_with_policy`.
+    ///
+    /// Panics if the value is already borrowed.
     #[inline]
-    pub fn borrow(&self) -> Borrow<T> {
-        Borrow {
-            _cell: self,
-            _value: self.take()
+    #[track_caller]
+    pub fn borrow(&self) -> Borrow<'_, T> {
+        self.borrow_with_policy(OnConflict::KeepBorrowed)
+    }
+
+    /// Like `borrow`, but return an error instead of panicking
+    /// if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn try_borrow(&self) -> Result<Borrow<'_, T>, BorrowError> {
+        self.try_borrow_with(T::placeholder)
+    }
+
+    /// Like `borrow`, with `on_conflict` deciding what happens
+    /// if the cell is written to before the guard returns its value.
+    #[inline]
+    #[track_caller]
+    pub fn borrow_with_policy<'a>(&'a self, on_conflict: OnConflict<'a, T>) -> Borrow<'a, T> {
+        self.borrow_with_policy_and(T::placeholder, on_conflict)
+    }
+}
+
+/// An error returned by `MoveCell::try_borrow` and `MoveCell::try_take`
+/// when the value is already moved out of the cell, or when the cell is poisoned.
+#[derive(Debug)]
+pub struct BorrowError {
+    location: Option<&'static Location<'static>>,
+    poisoned: bool,
+}
+
+impl BorrowError {
+    /// Return whether the cell is poisoned, rather than borrowed.
+    #[inline]
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned
+    }
+
+    /// Where the value was moved out, if the `track-borrows` feature is enabled.
+    #[inline]
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.poisoned {
+            return f.write_str("MoveCell poisoned by a panic during update")
+        }
+        f.write_str("MoveCell value already borrowed")?;
+        if let Some(location) = self.location {
+            write!(f, " at {}", location)?;
         }
+        Ok(())
     }
 }
 
-/// The cell’s contents are temporarily set to the default value during the clone.
-impl<T: Default + Clone> Clone for MoveCell<T> {
+#[cfg(feature = "std")]
+impl std::error::Error for BorrowError {}
+
+/// Panics if the value is moved out or mutably borrowed.
+impl<T: Clone> Clone for MoveCell<T> {
     #[inline]
     fn clone(&self) -> MoveCell<T> {
-        MoveCell::new(self.borrow().clone())
+        MoveCell::new(self.with_ref(T::clone))
     }
 }
 
-/// The cell’s contents are temporarily set to the default value during the formatting.
-impl<T: Default + fmt::Debug> fmt::Debug for MoveCell<T> {
+/// While the value is moved out or mutably borrowed, this prints `MoveCell(<borrowed>)`,
+/// with the borrow’s location if the `track-borrows` feature is enabled.
+/// A poisoned cell prints `MoveCell(<poisoned>)`.
+impl<T: fmt::Debug> fmt::Debug for MoveCell<T> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "MoveCell({:?})", *self.borrow())
+        match self.try_with_ref(|value| write!(f, "MoveCell({:?})", value)) {
+            Ok(result) => result,
+            Err(ref error) if error.poisoned => f.write_str("MoveCell(<poisoned>)"),
+            Err(error) => match error.location {
+                Some(location) => write!(f, "MoveCell(<borrowed at {}>)", location),
+                None => f.write_str("MoveCell(<borrowed>)"),
+            },
+        }
     }
 }
 
-/// The cell’s contents are temporarily set to the default value during the comparaison.
-impl<T: Default + Eq> Eq for MoveCell<T> {}
+/// A description of a `MoveCell`’s state, returned by `MoveCell::debug_state`.
+///
+/// Its `Display` output reads like “value currently borrowed at src/foo.rs:42”.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DebugState {
+    state: State,
+    location: Option<&'static Location<'static>>,
+}
+
+impl DebugState {
+    /// Return whether the value is currently moved out or lent in place.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        self.state.is_borrowed()
+    }
 
-/// The cell’s contents are temporarily set to the default value during the comparaison.
-impl<T: Default + PartialEq> PartialEq for MoveCell<T> {
+    /// Return where the value was last moved out or lent,
+    /// if the `track-borrows` feature is enabled and it wasn’t put back since.
+    #[inline]
+    pub fn location(&self) -> Option<&'static Location<'static>> {
+        self.location
+    }
+}
+
+impl fmt::Display for DebugState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match (self.state, self.location) {
+            (State::Available, None) => "value available",
+            (State::Available, Some(_)) => "value taken",
+            (State::Lent, _) | (State::Overwritten, _) | (State::InPlace, _) |
+            (State::Reading(_), _) => "value currently borrowed",
+            (State::Updating, _) => "value currently being updated",
+            (State::Poisoned, _) => "poisoned by a panic during update",
+            (State::Completed, _) => "future or stream completed",
+        })?;
+        if let Some(location) = self.location {
+            write!(f, " at {}", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Eq> Eq for MoveCell<T> {}
+
+/// Panics if either value is moved out or mutably borrowed.
+///
+/// The values are only read in place,
+/// so comparing a cell with itself compares its value with itself.
+impl<T: PartialEq> PartialEq for MoveCell<T> {
     #[inline]
     fn eq(&self, other: &MoveCell<T>) -> bool {
-        *self.borrow() == *other.borrow()
+        self.with_ref(|a| other.with_ref(|b| a == b))
+    }
+}
+
+/// Panics if either value is moved out or mutably borrowed.
+impl<T: PartialOrd> PartialOrd for MoveCell<T> {
+    #[inline]
+    fn partial_cmp(&self, other: &MoveCell<T>) -> Option<Ordering> {
+        self.with_ref(|a| other.with_ref(|b| a.partial_cmp(b)))
+    }
+
+    #[inline]
+    fn lt(&self, other: &MoveCell<T>) -> bool {
+        self.with_ref(|a| other.with_ref(|b| a < b))
     }
 
     #[inline]
-    fn ne(&self, other: &MoveCell<T>) -> bool {
-        *self.borrow() != *other.borrow()
+    fn le(&self, other: &MoveCell<T>) -> bool {
+        self.with_ref(|a| other.with_ref(|b| a <= b))
+    }
+
+    #[inline]
+    fn gt(&self, other: &MoveCell<T>) -> bool {
+        self.with_ref(|a| other.with_ref(|b| a > b))
+    }
+
+    #[inline]
+    fn ge(&self, other: &MoveCell<T>) -> bool {
+        self.with_ref(|a| other.with_ref(|b| a >= b))
+    }
+}
+
+/// Panics if either value is moved out or mutably borrowed.
+impl<T: Ord> Ord for MoveCell<T> {
+    #[inline]
+    fn cmp(&self, other: &MoveCell<T>) -> Ordering {
+        self.with_ref(|a| other.with_ref(|b| a.cmp(b)))
+    }
+}
+
+/// Panics if the value is moved out or mutably borrowed.
+impl<T: Hash> Hash for MoveCell<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.with_ref(|value| value.hash(state))
     }
 }
 
@@ -103,7 +957,28 @@ impl<T: Default + PartialEq> PartialEq for MoveCell<T> {
 /// When the wrapper is dropped, the value is returned to the cell automatically.
 pub struct Borrow<'a, T: 'a> {
     _cell: &'a MoveCell<T>,
-    _value: T,
+    _value: ManuallyDrop<T>,
+    _on_conflict: OnConflict<'a, T>,
+    /// The state before the value was moved out, `Available` or `Completed`.
+    _restore: State,
+}
+
+/// What a `Borrow` guard does when it returns its value
+/// to a cell that was written to while the guard was alive.
+pub enum OnConflict<'a, T: 'a> {
+    /// Keep the value written to the cell, then panic.
+    ///
+    /// With the `std` feature, if the guard is dropped while the thread is already panicking,
+    /// this only keeps the newer value, to avoid aborting the process.
+    Panic,
+    /// Keep the value written to the cell and drop the guard’s value.
+    KeepNewer,
+    /// Put the guard’s value in the cell and drop the newer value.
+    KeepBorrowed,
+    /// Put in the cell the result of calling the function
+    /// with the guard’s value and the newer value, in that order.
+    /// If the function panics, the process aborts.
+    Merge(&'a dyn Fn(T, T) -> T),
 }
 
 // Borrow intentionally does *not* implement Clone
@@ -111,18 +986,30 @@ pub struct Borrow<'a, T: 'a> {
 
 impl<'a, T> Borrow<'a, T> {
     /// Consume the `Borrow` guard and return the value.
+    /// The cell keeps its current contents.
     pub fn into_inner(self) -> T {
-        let value = unsafe { ptr::read(&self._value) };
+        self._cell.state.set(State::Available);
+        let value = unsafe { ptr::read(&*self._value) };
         mem::forget(self);
         value
     }
+
+    /// Return the value to the cell now, as dropping the guard would,
+    /// and return whether the cell was written to in the meantime.
+    pub fn commit(self) -> bool {
+        let cell = self._cell;
+        let value = unsafe { ptr::read(&*self._value) };
+        let on_conflict = unsafe { ptr::read(&self._on_conflict) };
+        let restore = self._restore;
+        mem::forget(self);
+        cell.give_back(value, &on_conflict, restore)
+    }
 }
 
 impl<'a, T> Drop for Borrow<'a, T> {
     fn drop(&mut self) {
-        // FIXME: make self._value a `ManuallyDrop` when that exists.
-        // https://github.com/rust-lang/rfcs/pull/197
-        mem::swap(&mut self._value, unsafe { &mut *self._cell.as_unsafe_cell().get() })
+        let value = unsafe { ManuallyDrop::take(&mut self._value) };
+        self._cell.give_back(value, &self._on_conflict, self._restore);
     }
 }
 
@@ -139,7 +1026,43 @@ impl<'a, T> ops::DerefMut for Borrow<'a, T> {
 impl<'a, T: fmt::Debug> fmt::Debug for Borrow<'a, T> {
     #[inline]
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "movecell::Borrow({:?})", self._value)
+        write!(f, "movecell::Borrow({:?})", *self._value)
+    }
+}
+
+/// A guard giving access in place to the value of a `MoveCell`,
+/// returned by `MoveCell::borrow_mut`.
+/// When the guard is dropped, other accesses to the cell are allowed again.
+pub struct BorrowMut<'a, T: 'a> {
+    _cell: &'a MoveCell<T>,
+    /// The state and location before the value was lent.
+    /// The state is `Available` or `Completed`.
+    _restore: State,
+    _restore_location: Option<&'static Location<'static>>,
+}
+
+impl<'a, T> Drop for BorrowMut<'a, T> {
+    fn drop(&mut self) {
+        debug_assert_eq!(self._cell.state.get(), State::InPlace);
+        self._cell.state.set(self._restore);
+        self._cell.set_moved_out_at(self._restore_location)
+    }
+}
+
+impl<'a, T> ops::Deref for BorrowMut<'a, T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T { unsafe { &*self._cell.value.get() } }
+}
+impl<'a, T> ops::DerefMut for BorrowMut<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T { unsafe { &mut *self._cell.value.get() } }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for BorrowMut<'a, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "movecell::BorrowMut({:?})", **self)
     }
 }
 
@@ -158,15 +1081,514 @@ fn it_works() {
     assert_eq!(&*x.borrow(), &Some("fifth".to_owned()));
     assert_eq!(x.borrow().as_ref().map(|s| s.len()), Some(5));
     assert_eq!(x.borrow().clone(), Some("fifth".to_owned()));
-    assert_eq!(x.borrow().is_some(), true);
-    assert_eq!(x.borrow().is_none(), false);
+    assert!(x.borrow().is_some());
+    assert!(!x.borrow().is_none());
     assert_eq!(x.clone(), x);
     assert_eq!(format!("{:?}", x), "MoveCell(Some(\"fifth\"))");
     assert_eq!(format!("{:?}", x.borrow()), "movecell::Borrow(Some(\"fifth\"))");
     assert_eq!(x.take(), Some("fifth".to_owned()));
-    assert_eq!(x.borrow().is_some(), false);
-    assert_eq!(x.borrow().is_none(), true);
+    assert!(!x.borrow().is_some());
+    assert!(x.borrow().is_none());
     assert_eq!(&*x.borrow(), &None);
     assert_eq!(x.clone(), x);
     assert_eq!(format!("{:?}", x), "MoveCell(None)");
 }
+
+#[test]
+#[allow(clippy::eq_op, clippy::nonminimal_bool)]
+fn partial_eq_aliasing() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::rc::Rc;
+
+    // Self-comparison
+    let x = MoveCell::new("first".to_owned());
+    assert!(x == x);
+    assert!(!(x != x));
+    assert_eq!(x.borrow().as_str(), "first");
+    let nan = MoveCell::new(f64::NAN);
+    assert!(nan != nan);
+    assert!(!(nan == nan));
+
+    // Distinct cells
+    let y = MoveCell::new("first".to_owned());
+    let z = MoveCell::new("second".to_owned());
+    assert!(x == y);
+    assert!(!(x != y));
+    assert!(x != z);
+    assert!(!(x == z));
+
+    // Nested cells
+    let nested = MoveCell::new(MoveCell::new("first".to_owned()));
+    assert!(nested == nested);
+    assert!(!(nested != nested));
+    assert_eq!(nested.borrow().borrow().as_str(), "first");
+
+    // Distinct outer cells sharing the same inner cell
+    let shared = Rc::new(MoveCell::new(1.5_f64));
+    let a = MoveCell::new(shared.clone());
+    let b = MoveCell::new(shared.clone());
+    assert!(a == b);
+    assert!(!(a != b));
+    assert_eq!(shared.take(), 1.5);
+
+    // Comparing while a `Borrow` guard is outstanding panics.
+    {
+        let guard = x.borrow();
+        assert_eq!(guard.as_str(), "first");
+        assert!(catch_unwind(AssertUnwindSafe(|| x == x)).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| x != y)).is_err());
+        assert!(catch_unwind(AssertUnwindSafe(|| y != x)).is_err());
+        assert!(y != z);
+    }
+    assert!(x == y);
+    assert!(x != z);
+}
+
+#[test]
+fn update() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[derive(Debug, PartialEq)]
+    enum Door {
+        Open(String),
+        Closed(String),
+    }
+
+    let x = MoveCell::new(Door::Closed("front".to_owned()));
+    x.update(|door| match door {
+        Door::Closed(name) => Door::Open(name),
+        door => door,
+    });
+    let was_open = x.update_with(|door| {
+        let was_open = match door { Door::Open(_) => true, Door::Closed(_) => false };
+        (door, was_open)
+    });
+    assert!(was_open);
+    assert!(!x.is_poisoned());
+    assert_eq!(x.into_inner(), Door::Open("front".to_owned()));
+
+    // Reentrant access panics and poisons the cell.
+    let x = MoveCell::new("first".to_owned());
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        x.update_with_policy(OnPanic::poison(), |value| {
+            x.replace("second".to_owned());
+            (value, ())
+        })
+    }));
+    assert!(result.is_err());
+    assert!(x.is_poisoned());
+    assert_eq!(format!("{:?}", x), "MoveCell(<poisoned>)");
+    assert!(x.try_take().unwrap_err().is_poisoned());
+    assert!(x.try_borrow_mut().unwrap_err().is_poisoned());
+    assert!(x.try_with_ref(|_| ()).unwrap_err().is_poisoned());
+    assert_eq!(x.try_borrow().unwrap_err().to_string(), "MoveCell poisoned by a panic during update");
+    assert!(catch_unwind(AssertUnwindSafe(|| x.replace("third".to_owned()))).is_err());
+    assert!(catch_unwind(AssertUnwindSafe(|| x.into_inner())).is_err());
+
+    // Restoring a fallback value
+    let x = MoveCell::new(vec![1, 2, 3]);
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        x.update_with_policy(OnPanic::Restore(vec![0]), |value| -> (Vec<i32>, ()) {
+            drop(value);
+            panic!("oops")
+        })
+    }));
+    assert!(result.is_err());
+    assert!(!x.is_poisoned());
+    assert_eq!(x.replace(vec![4]), vec![0]);
+    x.update_with_policy(OnPanic::Restore(vec![0]), |mut value| {
+        value.push(5);
+        (value, ())
+    });
+    assert_eq!(x.into_inner(), vec![4, 5]);
+}
+
+#[test]
+fn cyclic_references() {
+    struct Node<'a> {
+        next: MoveCell<Option<&'a Node<'a>>>,
+    }
+
+    let a = Node { next: MoveCell::new(None) };
+    let b = Node { next: MoveCell::new(Some(&a)) };
+    a.next.set(Some(&b));
+    assert!(ptr::eq(a.next.get().unwrap().next.get().unwrap(), &a));
+}
+
+#[test]
+fn try_borrow() {
+    let x = MoveCell::new(Some("first".to_owned()));
+    assert!(!x.is_borrowed());
+    {
+        let mut guard = x.try_borrow().unwrap();
+        assert!(x.is_borrowed());
+        assert!(x.try_borrow().is_err());
+        assert!(x.try_take().is_err());
+        if cfg!(not(feature = "track-borrows")) {
+            assert_eq!(x.try_take().unwrap_err().to_string(), "MoveCell value already borrowed");
+        }
+        assert_eq!(guard.take(), Some("first".to_owned()));
+        *guard = Some("second".to_owned());
+    }
+    assert!(!x.is_borrowed());
+    assert_eq!(x.try_take().unwrap(), Some("second".to_owned()));
+
+    let x = MoveCell::new(Some("third".to_owned()));
+    let guard = x.borrow();
+    assert_eq!(guard.into_inner(), Some("third".to_owned()));
+    assert!(!x.is_borrowed());
+    assert_eq!(x.take(), None);
+
+    let x = MoveCell::new(Some("fourth".to_owned()));
+    x.update(|value| {
+        assert!(x.is_borrowed());
+        assert!(x.try_borrow().is_err());
+        value
+    });
+    assert!(!x.is_poisoned());
+}
+
+#[test]
+fn leaked_guards() {
+    let mut x = MoveCell::new(vec![1]);
+    mem::forget(x.borrow());
+    assert!(x.is_borrowed());
+    assert_eq!(x.get_mut(), &vec![]);
+    assert!(!x.is_borrowed());
+    x.set(vec![2]);
+
+    mem::forget(x.borrow_mut());
+    assert!(x.try_take().is_err());
+    assert_eq!(x.into_inner(), vec![2]);
+}
+
+#[test]
+#[should_panic(expected = "MoveCell value already borrowed")]
+fn nested_borrow() {
+    let x = MoveCell::new(Some("first".to_owned()));
+    let _guard = x.borrow();
+    x.borrow();
+}
+
+#[test]
+fn conflict_policy() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let x = MoveCell::new(vec![1]);
+    let guard = x.borrow();
+    assert!(!guard.commit());
+    assert_eq!(x.take(), vec![1]);
+
+    let x = MoveCell::new(vec![1]);
+    {
+        let _guard = x.borrow();
+        assert_eq!(x.replace(vec![2]), vec![]);
+        assert_eq!(x.replace(vec![3]), vec![2]);
+        assert!(x.is_borrowed());
+    }
+    assert_eq!(x.take(), vec![1]);
+
+    let x = MoveCell::new(vec![1]);
+    let guard = x.borrow_with_policy(OnConflict::KeepNewer);
+    x.replace(vec![2]);
+    assert!(guard.commit());
+    assert_eq!(x.take(), vec![2]);
+
+    let x = MoveCell::new(vec![1]);
+    let guard = x.borrow_with_policy(OnConflict::KeepBorrowed);
+    x.replace(vec![2]);
+    assert!(guard.commit());
+    assert_eq!(x.take(), vec![1]);
+
+    let x = MoveCell::new(vec![1]);
+    let concat = |mut a: Vec<i32>, b: Vec<i32>| { a.extend(b); a };
+    {
+        let mut guard = x.borrow_with_policy(OnConflict::Merge(&concat));
+        guard.push(2);
+        x.replace(vec![3]);
+    }
+    assert_eq!(x.take(), vec![1, 2, 3]);
+
+    let x = MoveCell::new(vec![1]);
+    let guard = x.borrow_with_policy(OnConflict::Panic);
+    x.replace(vec![2]);
+    assert!(catch_unwind(AssertUnwindSafe(|| guard.commit())).is_err());
+    assert!(!x.is_borrowed());
+    assert_eq!(x.take(), vec![2]);
+
+    // Dropping the guard during unwinding does not panic again.
+    if cfg!(feature = "std") {
+        let x = MoveCell::new(vec![1]);
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let _guard = x.borrow_with_policy(OnConflict::Panic);
+            x.replace(vec![2]);
+            panic!("oops")
+        }));
+        assert!(result.is_err());
+        assert!(!x.is_borrowed());
+        assert_eq!(x.take(), vec![2]);
+    }
+
+    let x = MoveCell::new(vec![1]);
+    let guard = x.borrow_with_policy(OnConflict::Panic);
+    x.replace(vec![2]);
+    assert_eq!(guard.into_inner(), vec![1]);
+    assert_eq!(x.take(), vec![2]);
+}
+
+#[test]
+fn debug_state() {
+    let x = MoveCell::new(Some("first".to_owned()));
+    assert_eq!(x.debug_state().to_string(), "value available");
+    {
+        let _guard = x.borrow();
+        assert!(x.debug_state().is_borrowed());
+        if cfg!(not(feature = "track-borrows")) {
+            assert_eq!(format!("{:?}", x), "MoveCell(<borrowed>)");
+            assert_eq!(x.debug_state().to_string(), "value currently borrowed");
+        }
+    }
+    assert_eq!(x.debug_state().to_string(), "value available");
+    assert_eq!(format!("{:?}", x), "MoveCell(Some(\"first\"))");
+}
+
+#[cfg(feature = "track-borrows")]
+#[test]
+fn track_borrows() {
+    let x = MoveCell::new(Some("first".to_owned()));
+    assert_eq!(x.debug_state().location(), None);
+
+    let line = line!() + 1;
+    let guard = x.borrow();
+    let borrowed_at = format!("{}:{}:", file!(), line);
+    assert_eq!(x.debug_state().location().unwrap().line(), line);
+    assert!(x.debug_state().to_string()
+             .starts_with(&format!("value currently borrowed at {}", borrowed_at)));
+    assert!(format!("{:?}", x).starts_with(&format!("MoveCell(<borrowed at {}", borrowed_at)));
+    assert!(x.try_take().unwrap_err().to_string()
+             .starts_with(&format!("MoveCell value already borrowed at {}", borrowed_at)));
+    drop(guard);
+    assert_eq!(x.debug_state().location(), None);
+
+    let line = line!() + 1;
+    assert_eq!(x.take(), Some("first".to_owned()));
+    assert_eq!(x.debug_state().location().unwrap().line(), line);
+    assert!(x.debug_state().to_string().starts_with("value taken at "));
+
+    // An access during `with_ref` reports the reader, not the earlier `take`.
+    let line = line!() + 1;
+    x.with_ref(|_| {
+        assert_eq!(x.try_take().unwrap_err().location().unwrap().line(), line);
+    });
+    assert!(x.debug_state().to_string().starts_with("value taken at "));
+    x.with_mut(|_| ());
+    drop(x.borrow_mut());
+    assert!(x.debug_state().to_string().starts_with("value taken at "));
+    x.replace(Some("second".to_owned()));
+    assert_eq!(x.debug_state().location(), None);
+}
+
+#[test]
+fn with_mut() {
+    struct NoDefault(Vec<i32>);
+
+    let x = MoveCell::new(NoDefault(vec![1]));
+    let len = x.with_mut(|value| {
+        value.0.push(2);
+        assert!(x.is_borrowed());
+        assert!(x.try_with_mut(|_| ()).is_err());
+        assert!(x.try_borrow_mut().is_err());
+        value.0.len()
+    });
+    assert_eq!(len, 2);
+    assert!(!x.is_borrowed());
+    {
+        let mut guard = x.borrow_mut();
+        guard.0.push(3);
+        assert!(x.try_borrow_mut().is_err());
+    }
+    assert_eq!(x.into_inner().0, vec![1, 2, 3]);
+
+    let x = MoveCell::new(Some(1));
+    {
+        let guard = x.borrow_mut();
+        assert_eq!(format!("{:?}", guard), "movecell::BorrowMut(Some(1))");
+        assert!(x.try_borrow().is_err());
+        assert!(x.try_take().is_err());
+    }
+    assert_eq!(x.try_borrow_mut().map(|guard| *guard).unwrap(), Some(1));
+}
+
+#[test]
+#[should_panic(expected = "MoveCell value already borrowed")]
+fn replace_during_with_mut() {
+    let x = MoveCell::new(1);
+    x.with_mut(|_| x.replace(2));
+}
+
+#[test]
+fn placeholder() {
+    #[derive(Debug, PartialEq, Clone)]
+    enum Connection {
+        Closed,
+        Open(u32),
+    }
+
+    impl Placeholder for Connection {
+        fn placeholder() -> Connection {
+            Connection::Closed
+        }
+    }
+
+    let x = MoveCell::new(Connection::Open(1));
+    assert_eq!(format!("{:?}", x), "MoveCell(Open(1))");
+    assert!(x == x.clone());
+    {
+        let guard = x.borrow();
+        assert_eq!(*guard, Connection::Open(1));
+        assert_eq!(x.replace(Connection::Open(2)), Connection::Closed);
+    }
+    assert_eq!(x.take(), Connection::Open(1));
+    assert_eq!(x.take(), Connection::Closed);
+
+    struct Handle(u32);
+
+    let x = MoveCell::new(Handle(1));
+    assert_eq!(x.take_with(|| Handle(0)).0, 1);
+    {
+        let mut guard = x.borrow_with(|| Handle(u32::MAX));
+        assert_eq!(x.replace(Handle(2)).0, u32::MAX);
+        guard.0 += 10;
+        assert!(x.try_take_with(|| Handle(0)).is_err());
+        assert!(x.try_borrow_with(|| Handle(0)).is_err());
+    }
+    {
+        let guard = x.try_borrow_with(|| Handle(0)).unwrap();
+        assert_eq!(guard.0, 10);
+    }
+    {
+        let guard = x.borrow_with_policy_and(|| Handle(0), OnConflict::KeepNewer);
+        x.set(Handle(3));
+        assert_eq!(guard.0, 10);
+    }
+    assert_eq!(x.try_take_with(|| Handle(0)).unwrap().0, 3);
+    assert_eq!(x.into_inner().0, 0);
+}
+
+#[test]
+fn with_ref() {
+    struct NoPlaceholder(String);
+
+    impl Clone for NoPlaceholder {
+        fn clone(&self) -> NoPlaceholder {
+            NoPlaceholder(self.0.clone())
+        }
+    }
+
+    impl PartialEq for NoPlaceholder {
+        fn eq(&self, other: &NoPlaceholder) -> bool {
+            self.0 == other.0
+        }
+    }
+
+    impl fmt::Debug for NoPlaceholder {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    let x = MoveCell::new(NoPlaceholder("first".to_owned()));
+    let y = x.clone();
+    assert!(x == y);
+    assert_eq!(format!("{:?}", x), "MoveCell(\"first\")");
+    x.with_ref(|a| {
+        assert!(x.is_borrowed());
+        assert_eq!(x.with_ref(|b| a.0.len() + b.0.len()), 10);
+        assert!(x.try_with_mut(|_| ()).is_err());
+        assert_eq!(format!("{:?}", x), "MoveCell(\"first\")");
+    });
+    assert!(!x.is_borrowed());
+    x.with_mut(|value| {
+        value.0.push('!');
+        if cfg!(not(feature = "track-borrows")) {
+            assert_eq!(format!("{:?}", x), "MoveCell(<borrowed>)");
+        }
+        assert!(x.try_with_ref(|_| ()).is_err());
+    });
+    assert!(x != y);
+
+    let x = MoveCell::new(Some(1));
+    let _guard = x.borrow();
+    assert!(x.try_with_ref(|_| ()).is_err());
+    if cfg!(not(feature = "track-borrows")) {
+        assert_eq!(format!("{:?}", x), "MoveCell(<borrowed>)");
+    }
+}
+
+#[test]
+#[allow(clippy::eq_op, clippy::nonminimal_bool, clippy::neg_cmp_op_on_partial_ord)]
+#[allow(clippy::mutable_key_type)]
+fn ord_and_hash() {
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::{BTreeSet, HashSet};
+
+    fn hash<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    let a = MoveCell::new("a".to_owned());
+    let b = MoveCell::new("b".to_owned());
+    assert_eq!(a.cmp(&b), "a".cmp("b"));
+    assert_eq!(b.cmp(&a), Ordering::Greater);
+    assert_eq!(a.cmp(&a), Ordering::Equal);
+    assert_eq!(a.partial_cmp(&a), Some(Ordering::Equal));
+    assert!(a < b && a <= b && b > a && b >= a && a <= a && a >= a);
+    assert_eq!(hash(&a), hash(&"a".to_owned()));
+    assert_eq!(hash(&a), hash(&a.clone()));
+
+    let nan = MoveCell::new(f64::NAN);
+    let one = MoveCell::new(1.);
+    assert_eq!(nan.partial_cmp(&nan), None);
+    assert!(!(nan < one) && !(nan <= one) && !(nan > one) && !(nan >= one));
+    assert!(!(nan <= nan) && !(nan >= nan));
+
+    let set: BTreeSet<_> = vec![b.clone(), a.clone(), b.clone()].into_iter().collect();
+    assert_eq!(set.into_iter().map(MoveCell::into_inner).collect::<Vec<_>>(), ["a", "b"]);
+    let set: HashSet<_> = vec![b.clone(), a.clone(), b.clone()].into_iter().collect();
+    assert_eq!(set.len(), 2);
+    assert!(set.contains(&a));
+}
+
+#[test]
+fn cell_api() {
+    let mut x = MoveCell::from(vec![1]);
+    x.get_mut().push(2);
+    x.set(vec![3]);
+    let y = MoveCell::new(vec![4]);
+    x.swap(&y);
+    x.swap(&x);
+    assert_eq!(y.take(), vec![3]);
+    assert_eq!(unsafe { &*x.as_ptr() }, &vec![4]);
+    assert_eq!(x.as_cell().take(), vec![4]);
+    assert_eq!(x.into_cell().into_inner(), vec![]);
+
+    let x = MoveCell::from_cell(Cell::new(5));
+    assert_eq!(x.get(), 5);
+    x.with_ref(|_| assert_eq!(x.get(), 5));
+    {
+        let _guard = x.borrow();
+        assert!(x.try_with_ref(|value| *value).is_err());
+    }
+    assert_eq!(x.get(), 5);
+}
+
+#[test]
+#[should_panic(expected = "MoveCell value already borrowed")]
+fn swap_borrowed() {
+    let x = MoveCell::new(Some(1));
+    let y = MoveCell::new(Some(2));
+    let _guard = x.borrow();
+    x.swap(&y);
+}
@@ -0,0 +1,116 @@
+use std::panic::Location;
+use std::thread::LocalKey;
+use {MoveCell, OnPanic, Placeholder};
+
+/// Convenience methods for thread-local `MoveCell`s,
+/// similar to those of `LocalKey<Cell<T>>` and `LocalKey<RefCell<T>>`.
+///
+/// Each method panics like the `MoveCell` method of the same name,
+/// or if the thread-local value is being destroyed.
+pub trait LocalKeyExt<T: 'static> {
+    /// Return the inner value after replacing it with a placeholder value.
+    fn take(&'static self) -> T where T: Placeholder;
+
+    /// Return the inner value after replacing it with the given value.
+    fn replace(&'static self, value: T) -> T;
+
+    /// Set the inner value, dropping the previous one.
+    fn set(&'static self, value: T);
+
+    /// Replace the inner value with the result of calling `f` with the current value.
+    fn update<F>(&'static self, f: F) where F: FnOnce(T) -> T;
+
+    /// Lend a shared reference to the inner value to `f`.
+    fn with_borrow<F, R>(&'static self, f: F) -> R where F: FnOnce(&T) -> R;
+
+    /// Lend the inner value to `f` in place.
+    fn with_borrow_mut<F, R>(&'static self, f: F) -> R where F: FnOnce(&mut T) -> R;
+}
+
+impl<T: 'static> LocalKeyExt<T> for LocalKey<MoveCell<T>> {
+    // `LocalKey::with` does not pass the caller’s location on to its closure,
+    // so it is captured here for the borrow tracking.
+
+    #[inline]
+    #[track_caller]
+    fn take(&'static self) -> T where T: Placeholder {
+        let location = Location::caller();
+        self.with(|cell| cell.take_at(location))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn replace(&'static self, value: T) -> T {
+        self.with(|cell| cell.replace(value))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn set(&'static self, value: T) {
+        self.with(|cell| cell.set(value))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn update<F>(&'static self, f: F) where F: FnOnce(T) -> T {
+        let location = Location::caller();
+        self.with(|cell| cell.update_at(OnPanic::Abort, |value| (f(value), ()), location))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn with_borrow<F, R>(&'static self, f: F) -> R where F: FnOnce(&T) -> R {
+        let location = Location::caller();
+        self.with(|cell| cell.with_ref_at(f, location))
+    }
+
+    #[inline]
+    #[track_caller]
+    fn with_borrow_mut<F, R>(&'static self, f: F) -> R where F: FnOnce(&mut T) -> R {
+        let location = Location::caller();
+        self.with(|cell| cell.with_mut_at(f, location))
+    }
+}
+
+
+#[test]
+fn thread_local() {
+    thread_local! {
+        static JOBS: MoveCell<Vec<u32>> = const { MoveCell::new(Vec::new()) };
+    }
+
+    JOBS.with_borrow_mut(|jobs| jobs.push(1));
+    JOBS.update(|mut jobs| {
+        jobs.push(2);
+        jobs
+    });
+    assert_eq!(JOBS.with_borrow(|jobs| jobs.len()), 2);
+    assert_eq!(JOBS.replace(vec![3]), [1, 2]);
+    JOBS.set(vec![4]);
+    ::std::thread::spawn(|| assert_eq!(JOBS.take(), [])).join().unwrap();
+    assert_eq!(JOBS.take(), [4]);
+    assert_eq!(JOBS.take(), []);
+}
+
+#[test]
+#[cfg(feature = "track-borrows")]
+fn thread_local_locations() {
+    thread_local! {
+        static X: MoveCell<Option<u32>> = const { MoveCell::new(Some(1)) };
+    }
+
+    let line = line!() + 1;
+    X.with_borrow_mut(|_| {
+        let error = X.with(|x| x.try_take().unwrap_err());
+        assert_eq!(error.location().unwrap().file(), file!());
+        assert_eq!(error.location().unwrap().line(), line);
+    });
+    X.with_borrow(|_| {
+        let location = X.with(|x| x.debug_state().location().unwrap());
+        assert_eq!(location.line(), line!() - 2);
+    });
+    let line = line!() + 1;
+    assert_eq!(X.take(), Some(1));
+    let location = X.with(|x| x.debug_state().location().unwrap());
+    assert_eq!((location.file(), location.line()), (file!(), line));
+}
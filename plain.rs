@@ -0,0 +1,195 @@
+use core::cell::{Cell, UnsafeCell};
+use core::fmt;
+use core::mem;
+use core::ptr;
+use {MoveCell, Placeholder};
+
+/// A `MoveCell` without borrow tracking, with the same in-memory representation as `T`.
+///
+/// Values are only moved in and out, never lent, so no state is needed next to the value.
+/// This makes zero-cost views possible: `&mut T` as `&PlainMoveCell<T>`,
+/// `&PlainMoveCell<T>` as `&Cell<T>` and back,
+/// and `&PlainMoveCell<[T]>` as `&[PlainMoveCell<T>]`.
+#[repr(transparent)]
+pub struct PlainMoveCell<T: ?Sized> {
+    value: UnsafeCell<T>,
+}
+
+impl<T> PlainMoveCell<T> {
+    /// Create a new `PlainMoveCell` containing the given value.
+    #[inline]
+    pub const fn new(value: T) -> PlainMoveCell<T> {
+        PlainMoveCell {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consume the `PlainMoveCell` and return the inner value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Return the inner value after replacing it with the given value.
+    #[inline]
+    pub fn replace(&self, new_value: T) -> T {
+        unsafe {
+            mem::replace(&mut *self.value.get(), new_value)
+        }
+    }
+
+    /// Set the inner value, dropping the previous one.
+    #[inline]
+    pub fn set(&self, value: T) {
+        drop(self.replace(value))
+    }
+
+    /// Swap the values of two cells.
+    #[inline]
+    pub fn swap(&self, other: &PlainMoveCell<T>) {
+        if ptr::eq(self, other) {
+            return
+        }
+        unsafe {
+            ptr::swap(self.value.get(), other.value.get())
+        }
+    }
+
+    /// Return the inner value after replacing it with the result of `placeholder`.
+    #[inline]
+    pub fn take_with<F>(&self, placeholder: F) -> T where F: FnOnce() -> T {
+        self.replace(placeholder())
+    }
+
+    /// Return a `PlainMoveCell` view of a `Cell`.
+    #[inline]
+    pub fn from_cell(cell: &Cell<T>) -> &PlainMoveCell<T> {
+        unsafe { &*(cell as *const Cell<T> as *const PlainMoveCell<T>) }
+    }
+
+    /// Consume the `PlainMoveCell` and return a `MoveCell` containing the inner value.
+    #[inline]
+    pub fn into_move_cell(self) -> MoveCell<T> {
+        MoveCell::new(self.into_inner())
+    }
+}
+
+impl<T: ?Sized> PlainMoveCell<T> {
+    /// Return a `PlainMoveCell` view of a mutable reference.
+    #[inline]
+    pub fn from_mut(value: &mut T) -> &PlainMoveCell<T> {
+        unsafe { &*(value as *mut T as *const PlainMoveCell<T>) }
+    }
+
+    /// Return a mutable reference to the inner value.
+    ///
+    /// This is statically guaranteed not to conflict with any other access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Returns a raw pointer to the inner value.
+    #[inline]
+    pub fn as_ptr(&self) -> *mut T {
+        self.value.get()
+    }
+
+    /// Return a `Cell` view of this cell, for code written against `Cell`.
+    #[inline]
+    pub fn as_cell(&self) -> &Cell<T> {
+        unsafe { &*(self as *const PlainMoveCell<T> as *const Cell<T>) }
+    }
+}
+
+impl<T> PlainMoveCell<[T]> {
+    /// Return a slice of cells, one for each item of the slice in this cell.
+    #[inline]
+    pub fn as_slice_of_cells(&self) -> &[PlainMoveCell<T>] {
+        unsafe { &*(self as *const PlainMoveCell<[T]> as *const [PlainMoveCell<T>]) }
+    }
+}
+
+/// Convenience methods for when there is a placeholder value.
+impl<T: Placeholder> PlainMoveCell<T> {
+    /// Return the inner value after replacing it with a placeholder value.
+    #[inline]
+    pub fn take(&self) -> T {
+        self.replace(T::placeholder())
+    }
+}
+
+impl<T: Copy> PlainMoveCell<T> {
+    /// Return a copy of the inner value.
+    #[inline]
+    pub fn get(&self) -> T {
+        unsafe { *self.value.get() }
+    }
+}
+
+impl<T: Copy> Clone for PlainMoveCell<T> {
+    #[inline]
+    fn clone(&self) -> PlainMoveCell<T> {
+        PlainMoveCell::new(self.get())
+    }
+}
+
+impl<T: Default> Default for PlainMoveCell<T> {
+    #[inline]
+    fn default() -> PlainMoveCell<T> {
+        PlainMoveCell::new(T::default())
+    }
+}
+
+impl<T> From<T> for PlainMoveCell<T> {
+    #[inline]
+    fn from(value: T) -> PlainMoveCell<T> {
+        PlainMoveCell::new(value)
+    }
+}
+
+impl<T: ?Sized> fmt::Debug for PlainMoveCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("PlainMoveCell { .. }")
+    }
+}
+
+
+#[test]
+fn plain_move_cell() {
+    let mut x = PlainMoveCell::from(vec![1]);
+    x.get_mut().push(2);
+    assert_eq!(x.replace(vec![3]), [1, 2]);
+    let y = PlainMoveCell::new(vec![4]);
+    x.swap(&y);
+    x.swap(&x);
+    assert_eq!(y.take(), [3]);
+    assert_eq!(x.take_with(|| vec![5]), [4]);
+    assert_eq!(unsafe { &*x.as_ptr() }, &[5]);
+    assert_eq!(x.into_move_cell().into_inner(), [5]);
+    assert_eq!(format!("{:?}", y), "PlainMoveCell { .. }");
+}
+
+#[test]
+fn plain_move_cell_views() {
+    let mut value = Some("first".to_owned());
+    {
+        let x = PlainMoveCell::from_mut(&mut value);
+        assert_eq!(x.take(), Some("first".to_owned()));
+        x.as_cell().set(Some("second".to_owned()));
+        let cell = Cell::new(Some("third".to_owned()));
+        PlainMoveCell::from_cell(&cell).swap(x);
+        assert_eq!(cell.into_inner(), Some("second".to_owned()));
+    }
+    assert_eq!(value, Some("third".to_owned()));
+
+    let mut items = [1, 2, 3];
+    {
+        let cells = PlainMoveCell::from_mut(&mut items[..]).as_slice_of_cells();
+        cells[0].swap(&cells[2]);
+        cells[1].set(cells[0].get() * 10);
+        assert_eq!(cells[1].clone().into_inner(), 30);
+    }
+    assert_eq!(items, [3, 30, 1]);
+    assert_eq!(mem::size_of::<PlainMoveCell<u64>>(), mem::size_of::<u64>());
+}
@@ -0,0 +1,63 @@
+use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+use {Borrow, BorrowMut, MoveCell};
+
+/// Serializing fails if the value is moved out or mutably borrowed, or if the cell is poisoned.
+impl<T: Serialize> Serialize for MoveCell<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.try_with_ref(|value| value.serialize(serializer)) {
+            Ok(result) => result,
+            Err(error) => Err(ser::Error::custom(error)),
+        }
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for MoveCell<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<MoveCell<T>, D::Error> {
+        T::deserialize(deserializer).map(MoveCell::new)
+    }
+}
+
+impl<'a, T: Serialize> Serialize for Borrow<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'a, T: Serialize> Serialize for BorrowMut<'a, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+
+#[test]
+fn serde() {
+    use serde_test::{assert_de_tokens, assert_ser_tokens, assert_ser_tokens_error, Token};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use OnPanic;
+
+    let x = MoveCell::new(Some(1_u32));
+    let tokens = [Token::Some, Token::U32(1)];
+    assert_ser_tokens(&x, &tokens);
+    assert_de_tokens(&x, &tokens);
+    x.with_ref(|_| assert_ser_tokens(&x, &tokens));
+    let guard = x.borrow();
+    assert_ser_tokens(&guard, &tokens);
+    drop(guard);
+    assert_ser_tokens(&x.borrow_mut(), &tokens);
+
+    // With `track-borrows`, the message also includes the borrow’s location.
+    if cfg!(not(feature = "track-borrows")) {
+        let error = "MoveCell value already borrowed";
+        x.with_mut(|_| assert_ser_tokens_error(&x, &[], error));
+        let _guard = x.borrow();
+        assert_ser_tokens_error(&x, &[], error);
+    }
+
+    let poisoned = MoveCell::new(Some(1_u32));
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        poisoned.update_with_policy(OnPanic::poison(), |_| -> (Option<u32>, ()) { panic!() })
+    }));
+    assert!(result.is_err());
+    assert_ser_tokens_error(&poisoned, &[], "MoveCell poisoned by a panic during update");
+}
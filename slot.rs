@@ -0,0 +1,243 @@
+use std::fmt;
+use std::sync::mpsc::{RecvError, SendError, TryRecvError};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+
+/// A thread-safe single-value mailbox.
+///
+/// Sending a value replaces the previous one if it wasn’t taken yet,
+/// like `MoveCell::replace`, and taking it leaves the slot empty.
+/// The slot counts how many values were overwritten before anyone took them.
+///
+/// A `Slot` can be shared directly, or split into `SlotSender` and `SlotReceiver` handles
+/// that also support blocking receive and disconnection.
+pub struct Slot<T> {
+    state: Mutex<State<T>>,
+    changed: Condvar,
+}
+
+struct State<T> {
+    value: Option<T>,
+    overwritten: usize,
+    senders: usize,
+    receiver: bool,
+}
+
+impl<T> Slot<T> {
+    /// Create a new empty `Slot`.
+    pub fn new() -> Slot<T> {
+        Slot {
+            state: Mutex::new(State {
+                value: None,
+                overwritten: 0,
+                senders: 0,
+                receiver: false,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    /// Put a value in the slot, and return the previous value if it wasn’t taken.
+    pub fn replace(&self, value: T) -> Option<T> {
+        let state = self.lock();
+        self.put(state, value)
+    }
+
+    /// Take the value out of the slot, if any.
+    pub fn take(&self) -> Option<T> {
+        self.lock().value.take()
+    }
+
+    /// Return how many values were overwritten before anyone took them,
+    /// since the slot was created.
+    pub fn overwritten(&self) -> usize {
+        self.lock().overwritten
+    }
+
+    /// Split the slot into a sending and a receiving handle.
+    pub fn split(self) -> (SlotSender<T>, SlotReceiver<T>) {
+        {
+            let mut state = self.lock();
+            state.senders = 1;
+            state.receiver = true;
+        }
+        let slot = Arc::new(self);
+        (SlotSender { slot: slot.clone() }, SlotReceiver { slot })
+    }
+
+    fn put(&self, mut state: MutexGuard<'_, State<T>>, value: T) -> Option<T> {
+        let previous = state.value.replace(value);
+        if previous.is_some() {
+            state.overwritten += 1;
+        }
+        drop(state);
+        self.changed.notify_all();
+        previous
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State<T>> {
+        // Values are only dropped outside of the lock, so it can’t be poisoned
+        // in the middle of an update.
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Slot<T> {
+        Slot::new()
+    }
+}
+
+impl<T> fmt::Debug for Slot<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.lock();
+        f.debug_struct("Slot")
+         .field("full", &state.value.is_some())
+         .field("overwritten", &state.overwritten)
+         .finish()
+    }
+}
+
+/// The sending half of a split `Slot`. It can be cloned to send from several threads.
+pub struct SlotSender<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> SlotSender<T> {
+    /// Put a value in the slot, and return the previous value if it wasn’t taken.
+    ///
+    /// Return an error with the value if the receiver was dropped.
+    pub fn send(&self, value: T) -> Result<Option<T>, SendError<T>> {
+        let state = self.slot.lock();
+        if !state.receiver {
+            return Err(SendError(value))
+        }
+        Ok(self.slot.put(state, value))
+    }
+}
+
+impl<T> Clone for SlotSender<T> {
+    fn clone(&self) -> SlotSender<T> {
+        self.slot.lock().senders += 1;
+        SlotSender { slot: self.slot.clone() }
+    }
+}
+
+impl<T> Drop for SlotSender<T> {
+    fn drop(&mut self) {
+        self.slot.lock().senders -= 1;
+        self.slot.changed.notify_all();
+    }
+}
+
+impl<T> fmt::Debug for SlotSender<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SlotSender { .. }")
+    }
+}
+
+/// The receiving half of a split `Slot`.
+pub struct SlotReceiver<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> SlotReceiver<T> {
+    /// Take the value out of the slot without blocking.
+    ///
+    /// Return an error if the slot is empty,
+    /// telling whether more values can be sent.
+    pub fn try_recv(&self) -> Result<T, TryRecvError> {
+        let mut state = self.slot.lock();
+        match state.value.take() {
+            Some(value) => Ok(value),
+            None if state.senders == 0 => Err(TryRecvError::Disconnected),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// Wait for a value in the slot and take it.
+    ///
+    /// Return an error if the slot is empty and all senders were dropped.
+    pub fn recv(&self) -> Result<T, RecvError> {
+        let mut state = self.slot.lock();
+        loop {
+            if let Some(value) = state.value.take() {
+                return Ok(value)
+            }
+            if state.senders == 0 {
+                return Err(RecvError)
+            }
+            state = self.slot.changed.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Return how many values were overwritten before being received,
+    /// since the slot was created.
+    pub fn overwritten(&self) -> usize {
+        self.slot.overwritten()
+    }
+}
+
+impl<T> Drop for SlotReceiver<T> {
+    fn drop(&mut self) {
+        self.slot.lock().receiver = false;
+    }
+}
+
+impl<T> fmt::Debug for SlotReceiver<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SlotReceiver { .. }")
+    }
+}
+
+
+#[test]
+fn slot() {
+    let slot = Slot::new();
+    assert_eq!(slot.take(), None);
+    assert_eq!(slot.replace(1), None);
+    assert_eq!(slot.replace(2), Some(1));
+    assert_eq!(slot.replace(3), Some(2));
+    assert_eq!(slot.overwritten(), 2);
+    assert_eq!(slot.take(), Some(3));
+    assert_eq!(slot.take(), None);
+
+    let (sender, receiver) = slot.split();
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+    assert_eq!(sender.send(4), Ok(None));
+    assert_eq!(sender.clone().send(5), Ok(Some(4)));
+    assert_eq!(receiver.overwritten(), 3);
+    assert_eq!(receiver.recv(), Ok(5));
+    sender.send(6).unwrap();
+    drop(sender);
+    assert_eq!(receiver.try_recv(), Ok(6));
+    assert_eq!(receiver.try_recv(), Err(TryRecvError::Disconnected));
+    assert_eq!(receiver.recv(), Err(RecvError));
+
+    let (sender, receiver) = Slot::new().split();
+    drop(receiver);
+    assert_eq!(sender.send(7), Err(SendError(7)));
+}
+
+#[test]
+fn slot_threads() {
+    use std::thread;
+
+    const VALUES: usize = 10_000;
+
+    let (sender, receiver) = Slot::new().split();
+    let producer = thread::spawn(move || {
+        for i in 1..VALUES + 1 {
+            sender.send(i).unwrap();
+        }
+    });
+    let mut received = 0;
+    let mut last = 0;
+    while let Ok(value) = receiver.recv() {
+        assert!(value > last);
+        last = value;
+        received += 1;
+    }
+    producer.join().unwrap();
+    assert_eq!(last, VALUES);
+    assert_eq!(received + receiver.overwritten(), VALUES);
+}
@@ -0,0 +1,315 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::hint;
+use core::mem::{self, ManuallyDrop};
+use core::ops;
+use core::ptr;
+use core::sync::atomic::{AtomicU8, Ordering};
+use {BorrowError, Placeholder};
+
+/// The cell contains its value.
+const AVAILABLE: u8 = 0;
+/// Flag set while a thread is swapping the value in or out, on top of one of the other states.
+const LOCKED: u8 = 1;
+/// The value was moved out by a `SyncBorrow` guard, the cell contains a placeholder.
+const LENT: u8 = 2;
+
+/// A thread-safe counterpart to `MoveCell`.
+///
+/// Values are only moved in and out while a lock is held,
+/// never while running user code, so the lock is only ever held very briefly.
+pub struct SyncMoveCell<T> {
+    value: UnsafeCell<T>,
+    state: AtomicU8,
+}
+
+unsafe impl<T: Send> Sync for SyncMoveCell<T> {}
+
+impl<T> SyncMoveCell<T> {
+    /// Create a new `SyncMoveCell` containing the given value.
+    #[inline]
+    pub const fn new(value: T) -> SyncMoveCell<T> {
+        SyncMoveCell {
+            value: UnsafeCell::new(value),
+            state: AtomicU8::new(AVAILABLE),
+        }
+    }
+
+    /// Consume the `SyncMoveCell` and return the inner value.
+    #[inline]
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+
+    /// Return a mutable reference to the inner value.
+    ///
+    /// This is statically guaranteed not to conflict with any other access.
+    #[inline]
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    /// Return the inner value after replacing it with the given value.
+    ///
+    /// While the value is lent out by a `SyncBorrow` guard,
+    /// this returns the placeholder that the cell contains in the meantime.
+    /// The value written is dropped when the guard returns its own.
+    #[inline]
+    pub fn replace(&self, new_value: T) -> T {
+        let state = self.lock();
+        let old_value = unsafe { mem::replace(&mut *self.value.get(), new_value) };
+        self.unlock(state);
+        old_value
+    }
+
+    /// Return whether the value is currently lent out by a `SyncBorrow` guard.
+    #[inline]
+    pub fn is_borrowed(&self) -> bool {
+        self.state.load(Ordering::Relaxed) & LENT != 0
+    }
+
+    /// Return the inner value after replacing it with the result of `placeholder`,
+    /// or an error if the value is currently borrowed.
+    #[inline]
+    fn try_take_with<F>(&self, placeholder: F) -> Result<T, BorrowError> where F: FnOnce() -> T {
+        let placeholder = placeholder();
+        let state = self.lock();
+        if state == LENT {
+            self.unlock(state);
+            return Err(BorrowError { location: None, poisoned: false })
+        }
+        let value = unsafe { mem::replace(&mut *self.value.get(), placeholder) };
+        self.unlock(AVAILABLE);
+        Ok(value)
+    }
+
+    /// Wait until no other thread holds the lock, then take it.
+    /// Return the state to restore when unlocking.
+    #[inline]
+    fn lock(&self) -> u8 {
+        let mut spins = 0_u32;
+        loop {
+            let state = self.state.load(Ordering::Relaxed);
+            if state & LOCKED == 0 && self.state.compare_exchange_weak(
+                state, state | LOCKED, Ordering::Acquire, Ordering::Relaxed
+            ).is_ok() {
+                return state
+            }
+            spins += 1;
+            backoff(spins)
+        }
+    }
+
+    #[inline]
+    fn unlock(&self, state: u8) {
+        self.state.store(state, Ordering::Release)
+    }
+}
+
+/// Convenience methods for when there is a placeholder value.
+impl<T: Placeholder> SyncMoveCell<T> {
+    /// Return the inner value after replacing it with a placeholder value.
+    ///
+    /// Panics if the value is currently borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn take(&self) -> T {
+        match self.try_take() {
+            Ok(value) => value,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Return the inner value after replacing it with a placeholder value,
+    /// or an error if the value is currently borrowed.
+    #[inline]
+    pub fn try_take(&self) -> Result<T, BorrowError> {
+        self.try_take_with(T::placeholder)
+    }
+
+    /// Take the value, and return it in a `SyncBorrow` guard that will return it when dropped.
+    /// The cell’s contents are set to a placeholder value until the guard is dropped.
+    ///
+    /// Panics if the value is already borrowed.
+    #[inline]
+    #[track_caller]
+    pub fn borrow(&self) -> SyncBorrow<'_, T> {
+        match self.try_borrow() {
+            Ok(borrow) => borrow,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `borrow`, but return an error instead of panicking
+    /// if the value is already borrowed.
+    #[inline]
+    pub fn try_borrow(&self) -> Result<SyncBorrow<'_, T>, BorrowError> {
+        let placeholder = T::placeholder();
+        let state = self.lock();
+        if state == LENT {
+            self.unlock(state);
+            return Err(BorrowError { location: None, poisoned: false })
+        }
+        let value = unsafe { mem::replace(&mut *self.value.get(), placeholder) };
+        self.unlock(LENT);
+        Ok(SyncBorrow {
+            _cell: self,
+            _value: ManuallyDrop::new(value),
+        })
+    }
+}
+
+/// Wait a little before trying again to take a lock.
+/// If the lock holder was preempted, give it a chance to run.
+#[inline]
+fn backoff(spins: u32) {
+    #[cfg(feature = "std")]
+    {
+        if spins > 100 {
+            return std::thread::yield_now()
+        }
+    }
+    #[cfg(not(feature = "std"))]
+    let _ = spins;
+    hint::spin_loop()
+}
+
+impl<T: Default> Default for SyncMoveCell<T> {
+    #[inline]
+    fn default() -> SyncMoveCell<T> {
+        SyncMoveCell::new(T::default())
+    }
+}
+
+impl<T> fmt::Debug for SyncMoveCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SyncMoveCell { .. }")
+    }
+}
+
+/// A wrapper for a value "borrowed" from a `SyncMoveCell`.
+/// When the wrapper is dropped, the value is returned to the cell automatically.
+///
+/// The guard can be sent to another thread and dropped there.
+pub struct SyncBorrow<'a, T: 'a> {
+    _cell: &'a SyncMoveCell<T>,
+    _value: ManuallyDrop<T>,
+}
+
+impl<'a, T> SyncBorrow<'a, T> {
+    /// Consume the `SyncBorrow` guard and return the value.
+    /// The cell keeps its current contents.
+    pub fn into_inner(self) -> T {
+        let state = self._cell.lock();
+        debug_assert_eq!(state, LENT);
+        self._cell.unlock(AVAILABLE);
+        let value = unsafe { ptr::read(&*self._value) };
+        mem::forget(self);
+        value
+    }
+}
+
+impl<'a, T> Drop for SyncBorrow<'a, T> {
+    fn drop(&mut self) {
+        let value = unsafe { ManuallyDrop::take(&mut self._value) };
+        let state = self._cell.lock();
+        debug_assert_eq!(state, LENT);
+        let placeholder = unsafe { mem::replace(&mut *self._cell.value.get(), value) };
+        self._cell.unlock(AVAILABLE);
+        drop(placeholder)
+    }
+}
+
+impl<'a, T> ops::Deref for SyncBorrow<'a, T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T { &self._value }
+}
+impl<'a, T> ops::DerefMut for SyncBorrow<'a, T> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T { &mut self._value }
+}
+
+impl<'a, T: fmt::Debug> fmt::Debug for SyncBorrow<'a, T> {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "movecell::SyncBorrow({:?})", *self._value)
+    }
+}
+
+
+#[test]
+fn sync_move_cell() {
+    let x = SyncMoveCell::new(Some("first".to_owned()));
+    assert_eq!(x.replace(Some("second".to_owned())), Some("first".to_owned()));
+    {
+        let mut guard = x.borrow();
+        assert!(x.is_borrowed());
+        // Still lent while another thread holds the lock.
+        let state = x.lock();
+        assert!(x.is_borrowed());
+        x.unlock(state);
+        assert!(x.try_borrow().is_err());
+        assert!(x.try_take().is_err());
+        assert_eq!(x.replace(Some("dropped".to_owned())), None);
+        assert_eq!(format!("{:?}", guard), "movecell::SyncBorrow(Some(\"second\"))");
+        guard.as_mut().unwrap().push('!');
+    }
+    assert!(!x.is_borrowed());
+    let guard = x.borrow();
+    assert_eq!(guard.into_inner(), Some("second!".to_owned()));
+    assert_eq!(x.take(), None);
+    x.replace(Some("third".to_owned()));
+    assert_eq!(x.into_inner(), Some("third".to_owned()));
+}
+
+#[test]
+fn sync_move_cell_stress() {
+    use std::sync::atomic::AtomicUsize;
+    use std::thread;
+
+    const THREADS: usize = 8;
+    const ITERATIONS: usize = 10_000;
+
+    // Values are never lost or duplicated by concurrent take and replace:
+    // everything pushed ends up either in the cell or in a vector returned by `replace`.
+    let cell = SyncMoveCell::new(Vec::new());
+    let returned = AtomicUsize::new(0);
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for i in 0..ITERATIONS {
+                    let mut items = cell.take();
+                    items.push(i);
+                    let previous = cell.replace(items);
+                    returned.fetch_add(previous.len(), Ordering::Relaxed);
+                }
+            });
+        }
+    });
+    assert_eq!(cell.into_inner().len() + returned.into_inner(), THREADS * ITERATIONS);
+
+    // Guards always return their value, possibly from another thread.
+    let cell = SyncMoveCell::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..THREADS {
+            scope.spawn(|| {
+                for i in 0..ITERATIONS {
+                    let mut guard = loop {
+                        if let Ok(guard) = cell.try_borrow() {
+                            break guard
+                        }
+                        thread::yield_now()
+                    };
+                    guard.push(i);
+                    if i % 1000 == 0 {
+                        thread::scope(|scope| {
+                            scope.spawn(move || drop(guard));
+                        });
+                    }
+                }
+            });
+        }
+    });
+    assert_eq!(cell.into_inner().len(), THREADS * ITERATIONS);
+}
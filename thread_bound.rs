@@ -0,0 +1,186 @@
+use std::cell::Cell;
+use std::error;
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use MoveCell;
+
+/// No thread owns the cell.
+const RELEASED: usize = 0;
+
+/// A `MoveCell` that can be stored in `Sync` containers such as statics or `Arc`,
+/// but that may only be accessed from the thread that owns it.
+///
+/// The owner is the thread that created the cell.
+/// Ownership can be handed over with `release` and `claim`.
+pub struct ThreadBoundCell<T> {
+    cell: MoveCell<T>,
+    owner: AtomicUsize,
+    /// How many `with` calls are running. Only accessed by the owner thread.
+    active: Cell<usize>,
+}
+
+// Only the owner thread ever accesses `cell` and `active`.
+unsafe impl<T: Send> Sync for ThreadBoundCell<T> {}
+
+impl<T> ThreadBoundCell<T> {
+    /// Create a new `ThreadBoundCell` containing the given value,
+    /// owned by the current thread.
+    pub fn new(value: T) -> ThreadBoundCell<T> {
+        ThreadBoundCell {
+            cell: MoveCell::new(value),
+            owner: AtomicUsize::new(current_thread()),
+            active: Cell::new(0),
+        }
+    }
+
+    /// Consume the `ThreadBoundCell` and return the inner value.
+    ///
+    /// This can be called from any thread,
+    /// since having the cell by value excludes other accesses.
+    #[track_caller]
+    pub fn into_inner(self) -> T {
+        self.cell.into_inner()
+    }
+
+    /// Return a mutable reference to the inner `MoveCell`, from any thread.
+    pub fn get_mut(&mut self) -> &mut MoveCell<T> {
+        &mut self.cell
+    }
+
+    /// Call `f` with the inner `MoveCell`.
+    ///
+    /// Panics if the current thread does not own the cell.
+    #[track_caller]
+    pub fn with<F, R>(&self, f: F) -> R where F: FnOnce(&MoveCell<T>) -> R {
+        match self.try_with(f) {
+            Ok(result) => result,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Like `with`, but return an error instead of panicking
+    /// if the current thread does not own the cell.
+    pub fn try_with<F, R>(&self, f: F) -> Result<R, WrongThread> where F: FnOnce(&MoveCell<T>) -> R {
+        self.check_owner()?;
+        self.active.set(self.active.get() + 1);
+        let _guard = ActiveGuard { active: &self.active };
+        Ok(f(&self.cell))
+    }
+
+    /// Return whether the current thread owns the cell.
+    pub fn is_owned_by_current_thread(&self) -> bool {
+        self.owner.load(Ordering::Relaxed) == current_thread()
+    }
+
+    /// Give up ownership of the cell, so that another thread can `claim` it.
+    ///
+    /// Panics if the current thread does not own the cell,
+    /// or if called from within `with`.
+    #[track_caller]
+    pub fn release(&self) {
+        if let Err(error) = self.check_owner() {
+            panic!("{}", error)
+        }
+        assert!(self.active.get() == 0, "ThreadBoundCell released while in use");
+        self.owner.store(RELEASED, Ordering::Release)
+    }
+
+    /// Take ownership of a cell that was released by its previous owner.
+    ///
+    /// Return an error if another thread owns the cell.
+    pub fn claim(&self) -> Result<(), WrongThread> {
+        let current = current_thread();
+        match self.owner.compare_exchange(RELEASED, current, Ordering::Acquire, Ordering::Relaxed) {
+            Ok(_) => Ok(()),
+            Err(owner) if owner == current => Ok(()),
+            Err(_) => Err(WrongThread { _private: () }),
+        }
+    }
+
+    fn check_owner(&self) -> Result<(), WrongThread> {
+        if self.owner.load(Ordering::Acquire) == current_thread() {
+            Ok(())
+        } else {
+            Err(WrongThread { _private: () })
+        }
+    }
+}
+
+impl<T> fmt::Debug for ThreadBoundCell<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ThreadBoundCell { .. }")
+    }
+}
+
+struct ActiveGuard<'a> {
+    active: &'a Cell<usize>,
+}
+
+impl<'a> Drop for ActiveGuard<'a> {
+    fn drop(&mut self) {
+        self.active.set(self.active.get() - 1)
+    }
+}
+
+/// Return a non-zero number that identifies the current thread among running threads.
+///
+/// A number may be reused after its thread exits,
+/// which only lets a new thread access cells that their dead owner can no longer access.
+fn current_thread() -> usize {
+    thread_local!(static TOKEN: u8 = const { 0 });
+    TOKEN.with(|token| token as *const u8 as usize)
+}
+
+/// An error returned when a `ThreadBoundCell` is accessed
+/// from a thread that does not own it.
+#[derive(Debug)]
+pub struct WrongThread {
+    _private: (),
+}
+
+impl fmt::Display for WrongThread {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("ThreadBoundCell accessed from a thread that does not own it")
+    }
+}
+
+impl error::Error for WrongThread {}
+
+
+#[test]
+fn thread_bound_cell() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::Arc;
+    use std::thread;
+
+    let x = Arc::new(ThreadBoundCell::new(vec![1]));
+    x.with(|cell| cell.with_mut(|v| v.push(2)));
+    assert!(x.is_owned_by_current_thread());
+
+    let y = x.clone();
+    thread::spawn(move || {
+        assert!(!y.is_owned_by_current_thread());
+        assert!(y.try_with(|cell| cell.take()).is_err());
+        assert!(y.claim().is_err());
+    }).join().unwrap();
+
+    x.with(|cell| {
+        let result = catch_unwind(AssertUnwindSafe(|| x.release()));
+        assert!(result.is_err());
+        assert!(x.is_owned_by_current_thread());
+        cell.replace(vec![3]);
+    });
+    x.release();
+    assert!(x.try_with(|_| ()).is_err());
+
+    let y = x.clone();
+    thread::spawn(move || {
+        y.claim().unwrap();
+        assert_eq!(y.with(|cell| cell.replace(vec![4])), [3]);
+        y.release();
+    }).join().unwrap();
+
+    x.claim().unwrap();
+    let mut x = Arc::try_unwrap(x).unwrap();
+    assert_eq!(x.get_mut().take(), [4]);
+}
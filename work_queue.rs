@@ -0,0 +1,180 @@
+use alloc::collections::VecDeque;
+use core::cell::Cell;
+use core::fmt;
+use MoveCell;
+
+/// A queue of tasks that can be pushed to while it is being drained.
+///
+/// Every method takes `&self`, so a task being processed by `drain`
+/// can push more tasks to the same queue. They are processed by the same drain.
+pub struct WorkQueue<T> {
+    tasks: MoveCell<VecDeque<T>>,
+    order: QueueOrder,
+    draining: Cell<bool>,
+}
+
+/// In which order a `WorkQueue` processes its tasks.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum QueueOrder {
+    /// Oldest task first.
+    Fifo,
+    /// Newest task first.
+    Lifo,
+}
+
+impl<T> WorkQueue<T> {
+    /// Create an empty `WorkQueue` that processes tasks in the given order.
+    pub const fn new(order: QueueOrder) -> WorkQueue<T> {
+        WorkQueue {
+            tasks: MoveCell::new(VecDeque::new()),
+            order,
+            draining: Cell::new(false),
+        }
+    }
+
+    /// Return the order in which tasks are processed.
+    pub fn order(&self) -> QueueOrder {
+        self.order
+    }
+
+    /// Add a task to the queue. This can be called during `drain`.
+    pub fn push(&self, task: T) {
+        self.tasks.with_mut(|tasks| tasks.push_back(task))
+    }
+
+    /// Remove the next task from the queue, if any.
+    pub fn pop(&self) -> Option<T> {
+        self.tasks.with_mut(|tasks| match self.order {
+            QueueOrder::Fifo => tasks.pop_front(),
+            QueueOrder::Lifo => tasks.pop_back(),
+        })
+    }
+
+    /// Return the number of queued tasks.
+    pub fn len(&self) -> usize {
+        self.tasks.with_ref(|tasks| tasks.len())
+    }
+
+    /// Return whether no task is queued.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Return whether the queue is being drained.
+    pub fn is_draining(&self) -> bool {
+        self.draining.get()
+    }
+
+    /// Process tasks with `f` until the queue is empty, including tasks pushed in the meantime.
+    /// Return how many tasks were processed.
+    ///
+    /// Return an error if the queue is already being drained.
+    pub fn drain<F>(&self, f: F) -> Result<usize, DrainError> where F: FnMut(T) {
+        self.drain_max(usize::MAX, f)
+    }
+
+    /// Like `drain`, but stop after processing `max` tasks.
+    /// The remaining tasks stay in the queue.
+    pub fn drain_max<F>(&self, max: usize, mut f: F) -> Result<usize, DrainError> where F: FnMut(T) {
+        if self.draining.replace(true) {
+            return Err(DrainError { _private: () })
+        }
+        let _guard = DrainGuard { draining: &self.draining };
+        let mut processed = 0;
+        while processed < max {
+            match self.pop() {
+                Some(task) => f(task),
+                None => break,
+            }
+            processed += 1;
+        }
+        Ok(processed)
+    }
+}
+
+/// Ends a drain, including when a task unwinds.
+struct DrainGuard<'a> {
+    draining: &'a Cell<bool>,
+}
+
+impl<'a> Drop for DrainGuard<'a> {
+    fn drop(&mut self) {
+        self.draining.set(false)
+    }
+}
+
+impl<T> Default for WorkQueue<T> {
+    fn default() -> WorkQueue<T> {
+        WorkQueue::new(QueueOrder::Fifo)
+    }
+}
+
+impl<T> fmt::Debug for WorkQueue<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WorkQueue")
+            .field("order", &self.order)
+            .field("draining", &self.draining.get())
+            .finish_non_exhaustive()
+    }
+}
+
+/// An error returned by `WorkQueue::drain` when the queue is already being drained.
+#[derive(Debug)]
+pub struct DrainError {
+    _private: (),
+}
+
+impl fmt::Display for DrainError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("WorkQueue already being drained")
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DrainError {}
+
+
+#[test]
+fn work_queue() {
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let queue = WorkQueue::new(QueueOrder::Fifo);
+    queue.push(3);
+    queue.push(1);
+    let mut seen = Vec::new();
+    let processed = queue.drain(|n| {
+        seen.push(n);
+        if n > 1 {
+            queue.push(n - 1);
+            queue.push(n - 2);
+        }
+        assert!(queue.drain(|_| ()).is_err());
+    });
+    assert_eq!(processed.unwrap(), 6);
+    assert_eq!(seen, [3, 1, 2, 1, 1, 0]);
+    assert!(queue.is_empty());
+    assert!(!queue.is_draining());
+
+    let queue = WorkQueue::new(QueueOrder::Lifo);
+    queue.push(3);
+    queue.push(1);
+    let mut seen = Vec::new();
+    let processed = queue.drain_max(4, |n| {
+        seen.push(n);
+        if n > 1 {
+            queue.push(n - 1);
+            queue.push(n - 2);
+        }
+    });
+    assert_eq!(processed.unwrap(), 4);
+    assert_eq!(seen, [1, 3, 1, 2]);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(queue.pop(), Some(0));
+
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        queue.drain(|_| panic!())
+    }));
+    assert!(result.is_err());
+    assert!(!queue.is_draining());
+    assert_eq!(queue.drain(|_| ()).unwrap(), 0);
+}